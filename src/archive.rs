@@ -0,0 +1,598 @@
+use anyhow::{bail, Context, Result};
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsStr;
+use std::io::{BufReader, Read, Seek};
+use std::path::Component::{CurDir, Normal, ParentDir, Prefix, RootDir};
+use std::path::{Component, Path, PathBuf};
+use tar::Archive;
+use xz2::read::XzDecoder;
+use zip::ZipArchive;
+
+const MAX_FOLLOWED_SYMLINKS: usize = 40;
+
+// File type bits of a unix mode, as stored in zip archives.
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ArchiveFormat {
+    TarGz,
+    TarXz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// A rough estimate of how much larger an extracted Julia installation is
+    /// than an archive of it in this format.
+    pub fn expansion_factor(self) -> u64 {
+        match self {
+            ArchiveFormat::TarGz => 4,
+            ArchiveFormat::TarXz => 6,
+            ArchiveFormat::Zip => 3,
+        }
+    }
+}
+
+pub fn get_format_from_url(url: &str) -> Option<ArchiveFormat> {
+    let url_path = url.split(['?', '#']).next()?.to_ascii_lowercase();
+
+    if url_path.ends_with(".tar.gz") || url_path.ends_with(".tgz") {
+        Some(ArchiveFormat::TarGz)
+    } else if url_path.ends_with(".tar.xz") || url_path.ends_with(".txz") {
+        Some(ArchiveFormat::TarXz)
+    } else if url_path.ends_with(".zip") {
+        Some(ArchiveFormat::Zip)
+    } else {
+        None
+    }
+}
+
+fn get_format_from_magic_bytes(archive_path: &Path) -> Result<Option<ArchiveFormat>> {
+    let mut file = File::open(archive_path)
+        .with_context(|| format!("Failed to open archive `{}`.", archive_path.display()))?;
+
+    let mut magic = Vec::new();
+    file.by_ref().take(6).read_to_end(&mut magic)?;
+
+    let format = if magic.starts_with(&[0x1f, 0x8b]) {
+        Some(ArchiveFormat::TarGz)
+    } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        Some(ArchiveFormat::TarXz)
+    } else if magic.starts_with(b"PK\x03\x04") || magic.starts_with(b"PK\x05\x06") {
+        Some(ArchiveFormat::Zip)
+    } else {
+        None
+    };
+
+    Ok(format)
+}
+
+/// Determines the format of the archive at `archive_path` from the extension
+/// of the url it was downloaded from, or from its first bytes if the url
+/// doesn't have a known extension.
+pub fn detect_archive_format(url: &str, archive_path: &Path) -> Result<ArchiveFormat> {
+    if let Some(format) = get_format_from_url(url) {
+        return Ok(format);
+    }
+
+    match get_format_from_magic_bytes(archive_path)? {
+        Some(format) => Ok(format),
+        None => bail!(
+            "The file downloaded from url `{}` is not a .tar.gz, .tar.xz or .zip archive.",
+            url
+        ),
+    }
+}
+
+// All archives that we extract have a single top-level directory, which we
+// strip. This is also the place where all extractors apply the same rules to
+// the paths stored in an archive: anything that is not a plain relative path
+// aborts the extraction.
+fn get_path_sans_parent(path: &Path) -> Result<PathBuf> {
+    let mut components = path
+        .components()
+        .filter(|c| !matches!(c, CurDir)); // `./julia-1.6.2/bin` is a common way to store paths
+
+    if !matches!(components.next(), Some(Normal(_))) {
+        bail!("The archive contains the invalid path `{}`.", path.display());
+    }
+
+    let mut stripped_path = PathBuf::new();
+    for component in components {
+        match component {
+            Normal(name) => stripped_path.push(name),
+            _ => bail!(
+                "The archive contains the path `{}`, which points outside of the extraction folder.",
+                path.display()
+            ),
+        }
+    }
+
+    Ok(stripped_path)
+}
+
+// Resolves the target of the symlink at `link_path` (relative to the extraction
+// folder) without touching the file system, following the other symlinks of
+// the archive in `links`. Fails if the target, or any of the symlinks followed
+// on the way, points outside of the extraction folder.
+fn resolve_symlink_target(link_path: &Path, links: &HashMap<PathBuf, PathBuf>) -> Result<PathBuf> {
+    let mut resolved: Vec<&OsStr> = Vec::new();
+    if let Some(parent) = link_path.parent() {
+        resolved.extend(parent.iter());
+    }
+
+    let mut pending: VecDeque<Component> = links[link_path].components().collect();
+    let mut followed_links = 0;
+
+    while let Some(component) = pending.pop_front() {
+        match component {
+            CurDir => {}
+            ParentDir => {
+                if resolved.pop().is_none() {
+                    bail!(
+                        "The archive contains the symlink `{}`, which points outside of the extraction folder.",
+                        link_path.display()
+                    );
+                }
+            }
+            Normal(name) => {
+                resolved.push(name);
+                let current: PathBuf = resolved.iter().collect();
+
+                if let Some((link, target)) = links.get_key_value(&current) {
+                    followed_links += 1;
+                    if followed_links > MAX_FOLLOWED_SYMLINKS {
+                        bail!(
+                            "The archive contains the symlink `{}`, which can't be resolved because of a symlink loop.",
+                            link_path.display()
+                        );
+                    }
+
+                    resolved.pop();
+                    for component in target.components().rev() {
+                        pending.push_front(component);
+                    }
+                    if link.as_path() == link_path {
+                        bail!("The archive contains the symlink `{}`, which points to itself.", link_path.display());
+                    }
+                }
+            }
+            RootDir | Prefix(_) => bail!(
+                "The archive contains the symlink `{}` with the absolute target `{}`.",
+                link_path.display(),
+                links[link_path].display()
+            ),
+        }
+    }
+
+    Ok(resolved.iter().collect())
+}
+
+fn create_parent_dir(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create folder `{}`.", parent.display()))?;
+    }
+    Ok(())
+}
+
+// Keeps track of the links in an archive while it is extracted. Symlinks are
+// only created once all other entries have been extracted, so that nothing
+// in the archive can be written through a symlink.
+struct LinkTracker<'a> {
+    dst: &'a Path,
+    symlinks: HashMap<PathBuf, PathBuf>,
+}
+
+impl<'a> LinkTracker<'a> {
+    fn new(dst: &'a Path) -> Self {
+        LinkTracker {
+            dst,
+            symlinks: HashMap::new(),
+        }
+    }
+
+    // Returns the absolute path for `path`, which must not be inside a symlink from the archive.
+    fn get_target_path(&self, path: &Path) -> Result<PathBuf> {
+        for ancestor in path.ancestors().skip(1) {
+            if self.symlinks.contains_key(ancestor) {
+                bail!(
+                    "The archive contains the path `{}`, which would be written through the symlink `{}`.",
+                    path.display(),
+                    ancestor.display()
+                );
+            }
+        }
+
+        Ok(self.dst.join(path))
+    }
+
+    fn add_symlink(&mut self, path: PathBuf, target: PathBuf) -> Result<()> {
+        if target.as_os_str().is_empty() {
+            bail!("The archive contains the symlink `{}` without a target.", path.display());
+        }
+        self.get_target_path(&path)?;
+        self.symlinks.insert(path, target);
+        Ok(())
+    }
+
+    fn add_hard_link(&self, path: &Path, source: &Path) -> Result<()> {
+        let target_path = self.get_target_path(path)?;
+        let source_path = self.get_target_path(source)?;
+
+        if !std::fs::symlink_metadata(&source_path).map(|m| m.is_file()).unwrap_or(false) {
+            bail!(
+                "The archive contains the hard link `{}`, which does not point to a file that was extracted before.",
+                path.display()
+            );
+        }
+
+        create_parent_dir(&target_path)?;
+        std::fs::hard_link(&source_path, &target_path).with_context(|| {
+            format!("Failed to create hard link `{}`.", target_path.display())
+        })?;
+        Ok(())
+    }
+
+    fn create_symlinks(self) -> Result<()> {
+        for path in self.symlinks.keys() {
+            resolve_symlink_target(path, &self.symlinks)?;
+        }
+
+        for (path, target) in &self.symlinks {
+            let link_path = self.dst.join(path);
+            create_parent_dir(&link_path)?;
+            create_symlink(target, &link_path)
+                .with_context(|| format!("Failed to create symlink `{}`.", link_path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link_path: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link_path)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, link_path: &Path) -> std::io::Result<()> {
+    let resolved_target = link_path.parent().unwrap().join(target);
+    if resolved_target.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link_path)
+    } else {
+        std::os::windows::fs::symlink_file(target, link_path)
+    }
+}
+
+fn unpack_tar_sans_parent<R: Read>(mut archive: Archive<R>, dst: &Path) -> Result<()> {
+    let mut links = LinkTracker::new(dst);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        let path = get_path_sans_parent(&entry_path)?;
+        let kind = entry.header().entry_type();
+
+        if kind.is_pax_global_extensions() || kind.is_pax_local_extensions() || kind.is_gnu_longname() || kind.is_gnu_longlink() {
+            continue;
+        } else if kind.is_symlink() {
+            let target = entry.link_name()?.map(|t| t.into_owned()).unwrap_or_default();
+            links.add_symlink(path, target)?;
+        } else if kind.is_hard_link() {
+            let source = match entry.link_name()? {
+                Some(source) => get_path_sans_parent(&source)?,
+                None => bail!("The archive contains the hard link `{}` without a target.", entry_path.display()),
+            };
+            links.add_hard_link(&path, &source)?;
+        } else if kind.is_dir() || kind.is_file() || kind.is_contiguous() || kind.is_gnu_sparse() {
+            if path.as_os_str().is_empty() && !kind.is_dir() {
+                bail!("The archive contains the file `{}` outside of a top-level folder.", entry_path.display());
+            }
+            let target_path = links.get_target_path(&path)?;
+            create_parent_dir(&target_path)?;
+            entry.unpack(&target_path)?;
+        } else {
+            bail!("The archive contains `{}`, which is neither a file, a folder nor a link.", entry_path.display());
+        }
+    }
+
+    links.create_symlinks()
+}
+
+fn unpack_zip_sans_parent<R: Read + Seek>(mut archive: ZipArchive<R>, dst: &Path) -> Result<()> {
+    let mut links = LinkTracker::new(dst);
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let path = get_path_sans_parent(Path::new(file.name()))?;
+
+        if file.is_dir() {
+            let target_path = links.get_target_path(&path)?;
+            std::fs::create_dir_all(&target_path)
+                .with_context(|| format!("Failed to create folder `{}`.", target_path.display()))?;
+            continue;
+        }
+
+        if path.as_os_str().is_empty() {
+            bail!("The archive contains the file `{}` outside of a top-level folder.", file.name());
+        }
+
+        if file.unix_mode().is_some_and(|mode| mode & S_IFMT == S_IFLNK) {
+            let mut target = String::new();
+            file.read_to_string(&mut target)?;
+            links.add_symlink(path, PathBuf::from(target))?;
+            continue;
+        }
+
+        let target_path = links.get_target_path(&path)?;
+        create_parent_dir(&target_path)?;
+
+        let mut output = File::create(&target_path)
+            .with_context(|| format!("Failed to create file `{}`.", target_path.display()))?;
+        std::io::copy(&mut file, &mut output)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            if let Some(mode) = file.unix_mode() {
+                std::fs::set_permissions(&target_path, std::fs::Permissions::from_mode(mode & 0o777))?;
+            }
+        }
+    }
+
+    links.create_symlinks()
+}
+
+/// Extracts the archive at `archive_path` into `target_path`, without its
+/// top-level directory.
+pub fn extract_archive_sans_parent(
+    archive_path: &Path,
+    format: ArchiveFormat,
+    target_path: &Path,
+) -> Result<()> {
+    let file = File::open(archive_path)
+        .with_context(|| format!("Failed to open archive `{}`.", archive_path.display()))?;
+
+    match format {
+        ArchiveFormat::TarGz => {
+            unpack_tar_sans_parent(Archive::new(GzDecoder::new(BufReader::new(file))), target_path)
+        }
+        ArchiveFormat::TarXz => {
+            unpack_tar_sans_parent(Archive::new(XzDecoder::new(BufReader::new(file))), target_path)
+        }
+        ArchiveFormat::Zip => {
+            unpack_zip_sans_parent(ZipArchive::new(BufReader::new(file))?, target_path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use tar::EntryType::{Directory, Link, Regular, Symlink};
+    use xz2::write::XzEncoder;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    const FIXTURE_FILES: &[(&str, &[u8], u32)] = &[
+        ("julia-1.6.2/bin/julia", b"#!/bin/sh\necho julia\n", 0o755),
+        ("julia-1.6.2/share/julia/base/version.jl", b"VERSION = v\"1.6.2\"\n", 0o644),
+        ("julia-1.6.2/LICENSE.md", b"MIT\n", 0o644),
+    ];
+
+    fn write_tar_fixture<W: Write>(writer: W) -> W {
+        let mut builder = tar::Builder::new(writer);
+
+        let mut dir_header = tar::Header::new_gnu();
+        dir_header.set_entry_type(tar::EntryType::Directory);
+        dir_header.set_mode(0o755);
+        dir_header.set_size(0);
+        builder.append_data(&mut dir_header, "julia-1.6.2/", std::io::empty()).unwrap();
+
+        for (path, content, mode) in FIXTURE_FILES {
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(*mode);
+            header.set_size(content.len() as u64);
+            builder.append_data(&mut header, path, *content).unwrap();
+        }
+
+        builder.into_inner().unwrap()
+    }
+
+    fn write_fixture(dir: &Path, format: ArchiveFormat) -> PathBuf {
+        let path = dir.join("fixture");
+        let file = File::create(&path).unwrap();
+
+        match format {
+            ArchiveFormat::TarGz => {
+                write_tar_fixture(GzEncoder::new(file, flate2::Compression::default())).finish().unwrap();
+            }
+            ArchiveFormat::TarXz => {
+                write_tar_fixture(XzEncoder::new(file, 6)).finish().unwrap();
+            }
+            ArchiveFormat::Zip => {
+                let mut zip = ZipWriter::new(file);
+                zip.add_directory("julia-1.6.2/", FileOptions::default()).unwrap();
+                for (path, content, mode) in FIXTURE_FILES {
+                    zip.start_file(*path, FileOptions::default().unix_permissions(*mode)).unwrap();
+                    zip.write_all(content).unwrap();
+                }
+                zip.finish().unwrap();
+            }
+        }
+
+        path
+    }
+
+    fn check_extraction(format: ArchiveFormat) {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = write_fixture(dir.path(), format);
+        let target = dir.path().join("target");
+
+        assert_eq!(detect_archive_format("https://example.com/download", &archive_path).unwrap(), format);
+
+        extract_archive_sans_parent(&archive_path, format, &target).unwrap();
+
+        for (path, content, _mode) in FIXTURE_FILES {
+            let extracted = target.join(get_path_sans_parent(Path::new(path)).unwrap());
+            assert_eq!(&std::fs::read(&extracted).unwrap()[..], *content);
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mode = std::fs::metadata(&extracted).unwrap().permissions().mode();
+                assert_eq!(mode & 0o777, *_mode);
+            }
+        }
+        assert!(!target.join("julia-1.6.2").exists());
+    }
+
+    #[test]
+    fn test_extract_tar_gz() {
+        check_extraction(ArchiveFormat::TarGz);
+    }
+
+    #[test]
+    fn test_extract_tar_xz() {
+        check_extraction(ArchiveFormat::TarXz);
+    }
+
+    #[test]
+    fn test_extract_zip() {
+        check_extraction(ArchiveFormat::Zip);
+    }
+
+    // Appends an entry without any of the validation that `tar::Builder` does
+    // for paths, so that we can produce malicious archives.
+    fn append_raw(builder: &mut tar::Builder<Vec<u8>>, kind: tar::EntryType, path: &str, link_name: Option<&str>, content: &[u8]) {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+        if let Some(link_name) = link_name {
+            header.as_old_mut().linkname[..link_name.len()].copy_from_slice(link_name.as_bytes());
+        }
+        header.set_entry_type(kind);
+        header.set_mode(0o644);
+        header.set_size(content.len() as u64);
+        header.set_cksum();
+        builder.append(&header, content).unwrap();
+    }
+
+    fn extract_raw_tar(entries: &[(tar::EntryType, &str, Option<&str>)]) -> (tempfile::TempDir, Result<()>) {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (kind, path, link_name) in entries {
+            let content: &[u8] = if *kind == tar::EntryType::Regular { b"content" } else { b"" };
+            append_raw(&mut builder, *kind, path, *link_name, content);
+        }
+        let data = builder.into_inner().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target");
+        std::fs::create_dir(&target).unwrap();
+
+        let result = unpack_tar_sans_parent(Archive::new(&data[..]), &target);
+        (dir, result)
+    }
+
+    fn assert_rejected(entries: &[(tar::EntryType, &str, Option<&str>)]) {
+        let (dir, result) = extract_raw_tar(entries);
+
+        assert!(result.is_err());
+        let outside: Vec<_> = std::fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(outside, vec!["target"]);
+    }
+
+    #[test]
+    fn test_reject_path_traversal() {
+        assert_rejected(&[(Regular, "julia/../../evil", None)]);
+        assert_rejected(&[(Regular, "julia/bin/../../../evil", None)]);
+        assert_rejected(&[(Regular, "/evil", None)]);
+        assert_rejected(&[(Regular, "../evil", None)]);
+    }
+
+    #[test]
+    fn test_reject_files_outside_top_level_folder() {
+        assert_rejected(&[(Regular, "evil", None)]);
+    }
+
+    #[test]
+    fn test_reject_escaping_symlinks() {
+        assert_rejected(&[(Symlink, "julia/evil", Some("/etc"))]);
+        assert_rejected(&[(Symlink, "julia/evil", Some(".."))]);
+        assert_rejected(&[(Symlink, "julia/lib/evil", Some("../../target"))]);
+        // Each link stays inside on its own, but following the first one from the second escapes.
+        assert_rejected(&[
+            (Symlink, "julia/a/b/up", Some("../..")),
+            (Symlink, "julia/evil", Some("a/b/up/..")),
+        ]);
+        assert_rejected(&[(Symlink, "julia/loop1", Some("loop2")), (Symlink, "julia/loop2", Some("loop1/x"))]);
+    }
+
+    #[test]
+    fn test_reject_writing_through_symlinks() {
+        assert_rejected(&[
+            (Symlink, "julia/lib", Some("share")),
+            (Regular, "julia/lib/file", None),
+        ]);
+    }
+
+    #[test]
+    fn test_reject_escaping_hard_links() {
+        assert_rejected(&[(Link, "julia/evil", Some("/etc/passwd"))]);
+        assert_rejected(&[(Link, "julia/evil", Some("julia/../../etc/passwd"))]);
+        assert_rejected(&[(Link, "julia/evil", Some("julia/does-not-exist"))]);
+    }
+
+    #[test]
+    fn test_reject_special_files() {
+        assert_rejected(&[(tar::EntryType::Fifo, "julia/fifo", None)]);
+        assert_rejected(&[(tar::EntryType::Char, "julia/null", None)]);
+    }
+
+    #[test]
+    fn test_extract_internal_links() {
+        let (dir, result) = extract_raw_tar(&[
+            (Directory, "./julia-1.6.2/", None),
+            (Regular, "./julia-1.6.2/lib/libjulia.so.1.6", None),
+            (Symlink, "./julia-1.6.2/lib/libjulia.so", Some("libjulia.so.1.6")),
+            (Symlink, "./julia-1.6.2/bin/libdir", Some("../lib")),
+            (Link, "./julia-1.6.2/lib/libjulia-copy.so", Some("./julia-1.6.2/lib/libjulia.so.1.6")),
+        ]);
+        result.unwrap();
+
+        let target = dir.path().join("target");
+        assert_eq!(std::fs::read(target.join("lib/libjulia-copy.so")).unwrap(), b"content");
+
+        #[cfg(unix)]
+        {
+            assert_eq!(std::fs::read_link(target.join("lib/libjulia.so")).unwrap(), Path::new("libjulia.so.1.6"));
+            assert_eq!(std::fs::read(target.join("bin/libdir/libjulia.so")).unwrap(), b"content");
+        }
+    }
+
+    #[test]
+    fn test_reject_zip_path_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("evil.zip");
+        let mut zip = ZipWriter::new(File::create(&archive_path).unwrap());
+        zip.start_file("julia/../../evil", FileOptions::default()).unwrap();
+        zip.write_all(b"content").unwrap();
+        zip.finish().unwrap();
+
+        let target = dir.path().join("target");
+        assert!(extract_archive_sans_parent(&archive_path, ArchiveFormat::Zip, &target).is_err());
+        assert!(!dir.path().join("evil").exists());
+    }
+
+    #[test]
+    fn test_get_format_from_url() {
+        assert_eq!(get_format_from_url("https://julialang-s3.julialang.org/bin/linux/x64/1.6/julia-1.6.2-linux-x86_64.tar.gz"), Some(ArchiveFormat::TarGz));
+        assert_eq!(get_format_from_url("https://example.com/julia-1.6.2-linux-x86_64.tar.xz?raw=1"), Some(ArchiveFormat::TarXz));
+        assert_eq!(get_format_from_url("https://example.com/julia-1.6.2-win64.ZIP"), Some(ArchiveFormat::Zip));
+        assert_eq!(get_format_from_url("https://example.com/julia-1.6.2-win64.exe"), None);
+    }
+}
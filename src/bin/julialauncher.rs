@@ -1,17 +1,10 @@
-use anyhow::{anyhow, bail, Context, Result};
-use juliaup::config_file::{load_config_db, JuliaupConfig, JuliaupConfigChannel};
+use anyhow::{anyhow, Context, Result};
+use juliaup::config_file::{load_config_db, JuliaupConfigChannel};
 use juliaup::jsonstructs_versionsdb::JuliaupVersionDB;
+use juliaup::operations::get_julia_path_from_channel;
 use juliaup::utils::get_juliaupconfig_path;
 use juliaup::versions_file::load_versions_db;
-use normpath::PathExt;
 use std::path::Path;
-use std::path::PathBuf;
-
-#[derive(thiserror::Error, Debug)]
-pub enum JuliaupInvalidChannel {
-    #[error("Invalid channel specified")]
-    FromCmdLine(),
-}
 
 #[cfg(target_os = "windows")]
 mod bindings {
@@ -98,59 +91,6 @@ fn check_channel_uptodate(
     Ok(())
 }
 
-fn get_julia_path_from_channel(
-    versions_db: &JuliaupVersionDB,
-    config_data: &JuliaupConfig,
-    channel: &str,
-    juliaupconfig_path: &Path,
-    julia_version_from_cmd_line: bool,
-) -> Result<(PathBuf, Vec<String>)> {
-    let channel_info = if julia_version_from_cmd_line {
-        config_data
-            .installed_channels
-            .get(channel)
-            .ok_or_else(|| JuliaupInvalidChannel::FromCmdLine {})? // TODO #115 Handle this better in the main function
-    } else {
-        config_data.installed_channels.get(channel)
-            .ok_or_else(|| anyhow!("The juliaup configuration is in an inconsistent state, the currently configured default channel `{}` is not installed.", channel))?
-    };
-
-    match channel_info {
-        JuliaupConfigChannel::LinkedChannel { command, args } => {
-            return Ok((
-                PathBuf::from(command),
-                args.as_ref().map_or_else(|| Vec::new(), |v| v.clone()),
-            ))
-        }
-        JuliaupConfigChannel::SystemChannel { version } => {
-            let path = &config_data
-                .installed_versions.get(version)
-                .ok_or_else(|| anyhow!("The juliaup configuration is in an inconsistent state, the channel {} is pointing to Julia version {}, which is not installed.", channel, version))?.path;
-
-            check_channel_uptodate(channel, version, versions_db).with_context(|| {
-                format!(
-                    "The Julia launcher failed while checking whether the channe {} is up-to-date.",
-                    channel
-                )
-            })?;
-            let absolute_path = juliaupconfig_path
-                .parent()
-                .unwrap() // unwrap OK because there should always be a parent
-                .join(path)
-                .join("bin")
-                .join(format!("julia{}", std::env::consts::EXE_SUFFIX))
-                .normalize()
-                .with_context(|| {
-                    format!(
-                        "Failed to normalize path for Julia binary, starting from `{}`.",
-                        juliaupconfig_path.display()
-                    )
-                })?;
-            return Ok((absolute_path.into_path_buf(), Vec::new()));
-        }
-    }
-}
-
 fn run_app() -> Result<i32> {
     // Set console title
     if atty::is(atty::Stream::Stdout) {
@@ -183,8 +123,8 @@ fn run_app() -> Result<i32> {
     if args.len() > 1 {
         let first_arg = &args[1];
 
-        if first_arg.starts_with("+") {
-            julia_channel_to_use = Some(first_arg[1..].to_string());
+        if let Some(stripped) = first_arg.strip_prefix('+') {
+            julia_channel_to_use = Some(stripped.to_string());
             julia_version_from_cmd_line = true;
         }
     }
@@ -193,8 +133,18 @@ fn run_app() -> Result<i32> {
         anyhow!("The Julia launcher failed to figure out which juliaup channel to use.")
     })?;
 
+    if let Some(JuliaupConfigChannel::SystemChannel { version }) =
+        config_data.installed_channels.get(&julia_channel_to_use)
+    {
+        check_channel_uptodate(&julia_channel_to_use, version, &versiondb_data).with_context(|| {
+            format!(
+                "The Julia launcher failed while checking whether the channe {} is up-to-date.",
+                julia_channel_to_use
+            )
+        })?;
+    }
+
     let (julia_path, julia_args) = get_julia_path_from_channel(
-        &versiondb_data,
         &config_data,
         &julia_channel_to_use,
         &juliaupconfig_path,
@@ -224,10 +174,7 @@ fn run_app() -> Result<i32> {
         .status()
         .with_context(|| "The Julia launcher failed to start Julia.")?; // TODO Maybe include the command we actually tried to start?
 
-    let code = match status.code() {
-        Some(code) => code,
-        None => 1, // TODO #117 Is this the right call here? We get that if the sub process was terminated by a signal...
-    };
+    let code = status.code().unwrap_or(1); // TODO #117 Is this the right call here? We get that if the sub process was terminated by a signal...
 
     Ok(code)
 }
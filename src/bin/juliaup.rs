@@ -2,16 +2,47 @@ use juliaup::command_link::run_command_link;
 use juliaup::command_gc::run_command_gc;
 use juliaup::command_update::run_command_update;
 use juliaup::command_remove::run_command_remove;
-use clap::Clap;
-use anyhow::{Result};
+use clap::{Clap, IntoApp};
+use anyhow::{bail, Result};
+use std::path::PathBuf;
 use juliaup::command_add::run_command_add;
 use juliaup::command_default::run_command_default;
 use juliaup::command_status::run_command_status;
 use juliaup::command_initial_setup_from_launcher::run_command_initial_setup_from_launcher;
+use juliaup::command_cache::{run_command_cache_clean, run_command_cache_list};
+use juliaup::command_verify::run_command_verify;
+use juliaup::progress::init_progress;
+use juliaup::output::OutputFormat;
+use juliaup::command_info::run_command_info;
+use juliaup::command_list::run_command_list;
+use juliaup::command_which::run_command_which;
+use juliaup::command_run::run_command_run;
+use juliaup::command_matrix::run_command_matrix;
+use juliaup::command_env::run_command_env;
+use juliaup::command_rename::run_command_rename;
+use juliaup::command_copy::run_command_copy;
+use juliaup::command_init::run_command_init;
+use juliaup::command_completions::{run_command_complete_channels, run_command_completions};
+use juliaup::command_self::{run_command_self_uninstall, run_command_self_update};
 
 #[derive(Clap)]
 #[clap(name="Juliaup", version)]
 /// The Julia Version Manager
+struct Opts {
+    #[clap(long, short, global = true)]
+    /// Only print warnings and errors
+    quiet: bool,
+    #[clap(long, global = true, possible_values = &["auto", "bar", "plain", "none"])]
+    /// How to show download progress, `plain` prints one line every now and then
+    progress: Option<String>,
+    #[clap(long, global = true, possible_values = &["text", "json"])]
+    /// The format of the results that commands print
+    format: Option<String>,
+    #[clap(subcommand)]
+    command: Juliaup,
+}
+
+#[derive(Clap)]
 enum Juliaup {
     /// Set the default Julia version
     Default {
@@ -19,7 +50,19 @@ enum Juliaup {
     },
     /// Add a specific Julia version or channel to your system
     Add {
-        channel: String
+        channel: String,
+        #[clap(long)]
+        /// Don't check that the new Julia version starts after installing it
+        skip_smoke_test: bool,
+        #[clap(long, conflicts_with = "url")]
+        /// Install from a local archive or folder instead of downloading
+        from_file: Option<PathBuf>,
+        #[clap(long)]
+        /// Install a custom Julia archive from this url into a new channel
+        url: Option<String>,
+        #[clap(long)]
+        /// The expected SHA-256 checksum of the archive at `--url` or `--from-file`
+        sha256: Option<String>,
     },
     /// Link an existing Julia binary to a custom channel name
     Link {
@@ -27,39 +70,264 @@ enum Juliaup {
         file: String,
         args: Vec<String>
     },
+    /// Rename a channel
+    Rename {
+        old_channel: String,
+        new_channel: String,
+    },
+    /// Copy a channel under a new name
+    Copy {
+        channel: String,
+        new_channel: String,
+    },
     #[clap(alias="up")]
     /// Update all or a specific channel to the latest Julia version
     Update {
-        channel: Option<String>
+        channel: Option<String>,
+        #[clap(long)]
+        /// Don't check that new Julia versions start after installing them
+        skip_smoke_test: bool,
+        #[clap(long)]
+        /// Only show which channels would be updated and what would be downloaded and removed
+        dry_run: bool,
+        #[clap(long, short)]
+        /// Don't ask for confirmation
+        yes: bool,
     },
     #[clap(alias="rm")]
-    /// Remove a Julia version from your system
+    /// Remove Julia versions from your system
     Remove {
-        channel: String
+        #[clap(required_unless_present = "all-except", conflicts_with = "all-except")]
+        channels: Vec<String>,
+        #[clap(long)]
+        /// Remove all channels except this one
+        all_except: Option<String>,
+        #[clap(long)]
+        /// Also remove the default channel without asking, a new default is picked automatically
+        force: bool,
     },
     #[clap(alias="st")]
     /// Show all installed Julia versions
     Status {
     },
-    /// Garbage collect uninstalled Julia versions
+    #[clap(alias="ls")]
+    /// Show the Julia channels and versions that can be installed
+    List {
+        /// Only show versions in this semver range, e.g. `>=1.5, <1.7` or `~1.6`
+        range: Option<String>,
+        #[clap(long)]
+        /// Also show prereleases like betas and release candidates
+        prerelease: bool,
+        #[clap(long)]
+        /// Only show versions for this architecture, e.g. `x64` or `x86`
+        arch: Option<String>,
+    },
+    /// Show where juliaup keeps its files and a summary of the configuration
+    Info {
+    },
+    /// Show the Julia binary that `julia` starts for a channel
+    Which {
+        /// The channel to look up, the default channel if omitted
+        channel: Option<String>,
+    },
+    /// Run Julia from a specific channel, e.g. `juliaup run 1.6 -- script.jl`
+    Run {
+        channel: String,
+        #[clap(long)]
+        /// Install the channel first if it is not installed yet
+        install: bool,
+        #[clap(last = true)]
+        /// The arguments that are passed to Julia
+        args: Vec<String>,
+    },
+    /// Run Julia with the same arguments on several channels and report which ones passed
+    Matrix {
+        #[clap(required = true)]
+        channels: Vec<String>,
+        #[clap(long)]
+        /// Run all channels at the same time
+        parallel: bool,
+        #[clap(long)]
+        /// Also write the output of each channel to `<channel>.log` in this folder
+        log_dir: Option<PathBuf>,
+        #[clap(last = true)]
+        /// The arguments that are passed to Julia
+        args: Vec<String>,
+    },
+    /// Print the commands that put the Julia of a channel on the PATH
+    Env {
+        channel: String,
+        #[clap(long, possible_values = &["bash", "fish", "pwsh", "github"])]
+        /// The shell to print the commands for, `github` adds to `$GITHUB_PATH` and `$GITHUB_ENV` instead
+        shell: Option<String>,
+        #[clap(long)]
+        /// Install the channel first if it is not installed yet
+        install: bool,
+    },
+    /// Garbage collect uninstalled Julia versions and orphaned folders
     Gc {
+        #[clap(long)]
+        /// Only show what would be deleted
+        dry_run: bool,
+    },
+    /// Check installed Julia versions for missing, modified or extra files
+    Verify {
+        channel: Option<String>,
+        #[clap(long)]
+        /// Reinstall damaged versions without asking
+        reinstall: bool,
+    },
+    /// Manage the cache of downloaded Julia archives
+    Cache {
+        #[clap(subcommand)]
+        command: CacheCommand
+    },
+    /// Install juliaup into a bin folder, create the `julia` command and put it on the PATH
+    Init {
+        #[clap(long)]
+        /// The folder to install into, `~/.juliaup/bin` by default
+        bin_dir: Option<PathBuf>,
+        #[clap(long)]
+        /// Don't add the bin folder to the PATH in the shell startup files
+        no_modify_path: bool,
+        #[clap(long, short)]
+        /// Don't ask for confirmation
+        yes: bool,
+    },
+    /// Print the shell completion script for juliaup
+    Completions {
+        #[clap(possible_values = &["bash", "zsh", "fish", "powershell"])]
+        shell: String,
+    },
+    #[clap(name = "self")]
+    /// Manage this juliaup installation
+    SelfManagement {
+        #[clap(subcommand)]
+        command: SelfCommand
+    },
+    // Must match `COMPLETE_CHANNELS_COMMAND`, the completion scripts call it.
+    #[clap(name = "_complete-channels", setting(clap::AppSettings::Hidden))]
+    CompleteChannels {
+        #[clap(possible_values = &["installed", "available"])]
+        kind: String,
     },
     #[clap(name = "46029ef5-0b73-4a71-bff3-d0d05de42aac", setting(clap::AppSettings::Hidden))]
     InitialSetupFromLauncher {
     }
 }
 
+#[derive(Clap)]
+enum CacheCommand {
+    /// Show all cached downloads
+    List {
+    },
+    /// Delete all cached downloads
+    Clean {
+    }
+}
+
+#[derive(Clap)]
+enum SelfCommand {
+    /// Update juliaup itself to the newest release
+    Update {
+    },
+    /// Remove juliaup and the `julia` shim
+    Uninstall {
+        #[clap(long)]
+        /// Also remove all installed Julia versions and the juliaup configuration
+        remove_home: bool,
+        #[clap(long)]
+        /// Don't ask for confirmation
+        yes: bool,
+    }
+}
+
 fn main() -> Result<()> {
-    let args = Juliaup::parse();
+    let opts = Opts::parse();
+
+    init_progress(opts.quiet, opts.progress.as_deref());
+
+    let format = OutputFormat::from_name(opts.format.as_deref());
 
-    match args {
+    let supports_json = matches!(
+        opts.command,
+        Juliaup::Add {..} | Juliaup::Remove {..} | Juliaup::Status {} | Juliaup::Info {} | Juliaup::List {..}
+            | Juliaup::Update {..} | Juliaup::Which {..} | Juliaup::Matrix {..}
+    );
+
+    if format == OutputFormat::Json && !supports_json {
+        bail!("This command does not support `--format json`, only `status`, `info`, `list`, `which`, `matrix`, `add`, `update` and `remove` do.");
+    }
+
+    match opts.command {
         Juliaup::Default {channel} => run_command_default(channel),
-        Juliaup::Add {channel} => run_command_add(channel),
-        Juliaup::Remove {channel} => run_command_remove(channel),
-        Juliaup::Status {} => run_command_status(),
-        Juliaup::Update {channel} => run_command_update(channel),
-        Juliaup::Gc {} => run_command_gc(),
+        Juliaup::Add {channel, skip_smoke_test, from_file, url, sha256} => run_command_add(channel, skip_smoke_test, from_file, url, sha256, format),
+        Juliaup::Remove {channels, all_except, force} => run_command_remove(channels, all_except, force, format),
+        Juliaup::Status {} => run_command_status(format),
+        Juliaup::Info {} => run_command_info(format),
+        Juliaup::List {range, prerelease, arch} => run_command_list(prerelease, arch, range, format),
+        Juliaup::Update {channel, skip_smoke_test, dry_run, yes} => run_command_update(channel, skip_smoke_test, dry_run, yes, format),
+        Juliaup::Which {channel} => run_command_which(channel, format),
+        Juliaup::Run {channel, install, args} => {
+            let code = run_command_run(channel, args, install)?;
+
+            std::process::exit(code);
+        }
+        Juliaup::Matrix {channels, parallel, log_dir, args} => run_command_matrix(channels, args, parallel, log_dir, format),
+        Juliaup::Env {channel, shell, install} => run_command_env(channel, shell, install),
+        Juliaup::Gc {dry_run} => run_command_gc(dry_run),
+        Juliaup::Verify {channel, reinstall} => run_command_verify(channel, reinstall),
+        Juliaup::Cache {command} => match command {
+            CacheCommand::List {} => run_command_cache_list(),
+            CacheCommand::Clean {} => run_command_cache_clean(),
+        },
+        Juliaup::Init {bin_dir, no_modify_path, yes} => run_command_init(bin_dir, no_modify_path, yes),
+        Juliaup::Completions {shell} => run_command_completions(&Opts::into_app(), &shell),
+        Juliaup::CompleteChannels {kind} => run_command_complete_channels(&kind),
+        Juliaup::SelfManagement {command} => match command {
+            SelfCommand::Update {} => run_command_self_update(),
+            SelfCommand::Uninstall {remove_home, yes} => run_command_self_uninstall(remove_home, yes),
+        },
+        Juliaup::Rename {old_channel, new_channel} => run_command_rename(old_channel, new_channel),
+        Juliaup::Copy {channel, new_channel} => run_command_copy(channel, new_channel),
         Juliaup::Link {channel, file, args} => run_command_link(channel, file, args),
         Juliaup::InitialSetupFromLauncher {} => run_command_initial_setup_from_launcher()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use juliaup::command_completions::generate_completions;
+
+    #[test]
+    fn test_completions_for_juliaup() {
+        for shell in ["bash", "zsh", "fish", "powershell"] {
+            let script = generate_completions(&Opts::into_app(), shell).unwrap();
+            assert!(script.contains("_complete-channels"), "{}", shell);
+
+            if shell == "powershell" {
+                continue;
+            }
+
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("completions");
+            std::fs::write(&path, &script).unwrap();
+
+            if let Ok(output) = std::process::Command::new(shell).arg("-n").arg(&path).output() {
+                assert!(output.status.success(), "{}: {}", shell, String::from_utf8_lossy(&output.stderr));
+            }
+        }
+
+        let zsh = generate_completions(&Opts::into_app(), "zsh").unwrap();
+        for spec in [
+            "':channel:_juliaup_channels installed'",
+            "':channel:_juliaup_channels available'",
+            "'::channel:_juliaup_channels installed'",
+            "'*::channels:_juliaup_channels installed'",
+            "':old-channel:_juliaup_channels installed'",
+        ] {
+            assert!(zsh.contains(spec), "{}", spec);
+        }
+    }
+}
@@ -1,10 +1,93 @@
-use crate::operations::install_version;
-use crate::config_file::JuliaupConfigChannel;
+use crate::operations::{install_version, install_version_from_file, install_version_from_url};
+use crate::config_file::{JuliaupConfig, JuliaupConfigChannel};
 use crate::config_file::{load_config_db, save_config_db};
+use crate::output::{print_json, OutputFormat};
+use crate::utils::{check_url_channel_name, get_juliaup_home_path};
 use crate::versions_file::load_versions_db;
 use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Serialize)]
+struct AddResult {
+    #[serde(rename = "Channel")]
+    channel: String,
+    #[serde(rename = "Version")]
+    version: String,
+    #[serde(rename = "Path")]
+    path: PathBuf,
+    #[serde(rename = "IsDefault")]
+    is_default: bool,
+}
+
+fn print_add_result(format: OutputFormat, config_data: &JuliaupConfig, channel: &str, version: &str) -> Result<()> {
+    if format == OutputFormat::Json {
+        let home_path = get_juliaup_home_path()?;
+
+        print_json(&AddResult {
+            channel: channel.to_string(),
+            version: version.to_string(),
+            path: home_path.join(&config_data.installed_versions[version].path).components().collect(),
+            is_default: config_data.default.as_deref() == Some(channel),
+        })?;
+    }
+
+    Ok(())
+}
+
+fn add_channel_from_url(channel: String, url: String, sha256: Option<String>, skip_smoke_test: bool, format: OutputFormat) -> Result<()> {
+    check_url_channel_name(&channel)?;
+
+    let version_db =
+        load_versions_db().with_context(|| "`add` command failed to load versions db.")?;
+
+    let mut config_data =
+        load_config_db().with_context(|| "`add` command failed to load configuration file.")?;
+
+    if config_data.installed_channels.contains_key(&channel) {
+        bail!("'{}' is already installed.", &channel);
+    }
+
+    if version_db.available_channels.contains_key(&channel) {
+        eprintln!("WARNING: The channel name `{}` is also a system channel. By installing a custom Julia to this channel you are hiding this system channel.", channel);
+    }
+
+    // Versions installed from a url are not shared between channels.
+    let version = format!("url-{}", channel);
+
+    install_version_from_url(&version, &url, sha256.as_deref(), &mut config_data, !skip_smoke_test)?;
+
+    config_data.installed_channels.insert(
+        channel.clone(),
+        JuliaupConfigChannel::DirectDownloadChannel { version: version.clone(), url, sha256 },
+    );
+
+    if config_data.default.is_none() {
+        config_data.default = Some(channel.clone());
+    }
+
+    save_config_db(&config_data)
+        .with_context(|| format!("Failed to save configuration file from `add` command after '{}' was installed.", channel))?;
+
+    print_add_result(format, &config_data, &channel, &version)
+}
+
+pub fn run_command_add(
+    channel: String,
+    skip_smoke_test: bool,
+    from_file: Option<PathBuf>,
+    url: Option<String>,
+    sha256: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    if let Some(url) = url {
+        return add_channel_from_url(channel, url, sha256, skip_smoke_test, format);
+    }
+
+    if sha256.is_some() && from_file.is_none() {
+        bail!("`--sha256` can only be used together with `--url` or `--from-file`.");
+    }
 
-pub fn run_command_add(channel: String) -> Result<()> {
     let version_db =
         load_versions_db().with_context(|| "`add` command failed to load versions db.")?;
 
@@ -24,13 +107,18 @@ pub fn run_command_add(channel: String) -> Result<()> {
     if config_data.installed_channels.contains_key(&channel) {
         bail!("'{}' is already installed.", &channel);
     }
-    
-    install_version(&required_version, &mut config_data, &version_db)?;
+
+    match from_file {
+        Some(from_file) => {
+            install_version_from_file(&required_version, &from_file, sha256.as_deref(), &mut config_data, &version_db, !skip_smoke_test)?
+        }
+        None => install_version(&required_version, &mut config_data, &version_db, !skip_smoke_test)?,
+    }
 
     config_data.installed_channels.insert(
         channel.clone(),
         JuliaupConfigChannel::SystemChannel {
-            version: required_version,
+            version: required_version.clone(),
         },
     );
 
@@ -41,5 +129,5 @@ pub fn run_command_add(channel: String) -> Result<()> {
     save_config_db(&config_data)
         .with_context(|| format!("Failed to save configuration file from `add` command after '{}' was installed.", channel))?;
 
-    Ok(())
+    print_add_result(format, &config_data, &channel, &required_version)
 }
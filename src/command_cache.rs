@@ -0,0 +1,43 @@
+use crate::download_cache::{list_cached_files, remove_cached_file};
+use crate::progress::report_info;
+use anyhow::{Context, Result};
+use indicatif::HumanBytes;
+
+pub fn run_command_cache_list() -> Result<()> {
+    let files =
+        list_cached_files().with_context(|| "`cache list` command failed to read the download cache.")?;
+
+    if files.is_empty() {
+        report_info("The download cache is empty.");
+        return Ok(());
+    }
+
+    println!("Cached downloads:");
+
+    for file in &files {
+        print!("     {} ({})", file.path.display(), HumanBytes(file.size));
+        if file.partial {
+            print!(" (incomplete)");
+        }
+        println!();
+    }
+
+    println!("Total: {}", HumanBytes(files.iter().map(|f| f.size).sum()));
+
+    Ok(())
+}
+
+pub fn run_command_cache_clean() -> Result<()> {
+    let files =
+        list_cached_files().with_context(|| "`cache clean` command failed to read the download cache.")?;
+
+    let mut freed = 0;
+    for file in files {
+        remove_cached_file(&file.path)?;
+        freed += file.size;
+    }
+
+    report_info(format!("Removed all cached downloads, freeing {}.", HumanBytes(freed)));
+
+    Ok(())
+}
@@ -0,0 +1,330 @@
+use crate::config_file::load_config_db;
+use crate::versions_file::load_versions_db;
+use anyhow::{bail, Result};
+use clap::App;
+use clap_generate::generators::{Bash, Fish, PowerShell, Zsh};
+use clap_generate::{generate, Generator};
+
+/// The commands whose arguments are installed channels.
+const INSTALLED_CHANNEL_COMMANDS: [&str; 10] = ["default", "remove", "update", "which", "run", "matrix", "verify", "env", "rename", "copy"];
+
+/// The commands whose arguments are channels from the versions db.
+const AVAILABLE_CHANNEL_COMMANDS: [&str; 1] = ["add"];
+
+/// The name of the hidden command that the completion scripts call to get
+/// the channel names.
+pub const COMPLETE_CHANNELS_COMMAND: &str = "_complete-channels";
+
+/// Returns `installed` or `available` if the arguments of `command` are
+/// channels.
+fn get_channel_kind(command: &str) -> Option<&'static str> {
+    if INSTALLED_CHANNEL_COMMANDS.contains(&command) {
+        Some("installed")
+    } else if AVAILABLE_CHANNEL_COMMANDS.contains(&command) {
+        Some("available")
+    } else {
+        None
+    }
+}
+
+fn generate_static<G: Generator>(app: &App, bin_name: &str) -> String {
+    let mut app = app.clone();
+    let mut buffer = Vec::new();
+
+    generate::<G, _>(&mut app, bin_name, &mut buffer);
+
+    String::from_utf8_lossy(&buffer).into_owned()
+}
+
+// clap_generate only knows the static structure of the command line, so each
+// script gets a hook on top that asks `juliaup _complete-channels` for the
+// channel names.
+
+fn add_bash_channel_hook(script: String, bin_name: &str) -> String {
+    let function_name = format!("_{}", bin_name);
+
+    format!(
+        r#"{script}
+_{bin_name}_channels() {{
+    local i word cmd kind cur prev
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    cmd=""
+
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        word="${{COMP_WORDS[i]}}"
+        case "${{COMP_WORDS[i-1]}}" in
+            --progress|--format)
+                continue
+                ;;
+        esac
+        if [[ ${{word}} != -* ]]; then
+            cmd="${{word}}"
+            break
+        fi
+    done
+
+    case "${{cmd}}" in
+        {installed})
+            kind="installed"
+            ;;
+        {available})
+            kind="available"
+            ;;
+        *)
+            kind=""
+            ;;
+    esac
+
+    if [[ -n ${{kind}} && ${{cur}} != -* && ${{prev}} != -* ]]; then
+        COMPREPLY=( $(compgen -W "$({bin_name} {complete} ${{kind}} 2>/dev/null)" -- "${{cur}}") )
+        return 0
+    fi
+
+    {function_name}
+}}
+
+complete -F _{bin_name}_channels -o bashdefault -o default {bin_name}
+"#,
+        script = script,
+        bin_name = bin_name,
+        function_name = function_name,
+        installed = INSTALLED_CHANNEL_COMMANDS.join("|"),
+        available = AVAILABLE_CHANNEL_COMMANDS.join("|"),
+        complete = COMPLETE_CHANNELS_COMMAND,
+    )
+}
+
+fn add_zsh_channel_hook(script: String, bin_name: &str) -> String {
+    let mut lines = Vec::new();
+    let mut pending_kind = None;
+
+    for line in script.lines() {
+        let trimmed = line.trim();
+
+        if trimmed == "&& ret=0" {
+            pending_kind = None;
+        } else if let Some(command) = trimmed.strip_prefix('(').and_then(|rest| rest.strip_suffix(')')) {
+            pending_kind = get_channel_kind(command);
+        }
+
+        // The first positional argument of a channel command, e.g.
+        // `':channel:' \`, gets an action that lists the channels.
+        if let Some(kind) = pending_kind {
+            if (line.starts_with("':") || line.starts_with("'*:")) && line.ends_with(":' \\") {
+                let spec = &line[..line.len() - "' \\".len()];
+                lines.push(format!("{}_{}_channels {}' \\", spec, bin_name, kind));
+                pending_kind = None;
+                continue;
+            }
+        }
+
+        if line == format!("_{} \"$@\"", bin_name) {
+            lines.push(format!(
+                "(( $+functions[_{bin_name}_channels] )) ||\n_{bin_name}_channels() {{\n    local channels; channels=(${{(f)\"$({bin_name} {complete} $1 2>/dev/null)\"}})\n    compadd -a channels\n}}\n",
+                bin_name = bin_name,
+                complete = COMPLETE_CHANNELS_COMMAND,
+            ));
+        }
+
+        lines.push(line.to_string());
+    }
+
+    lines.join("\n") + "\n"
+}
+
+fn add_fish_channel_hook(mut script: String, bin_name: &str) -> String {
+    for (commands, kind) in [(&INSTALLED_CHANNEL_COMMANDS[..], "installed"), (&AVAILABLE_CHANNEL_COMMANDS[..], "available")] {
+        script.push_str(&format!(
+            "complete -c {bin_name} -n \"__fish_seen_subcommand_from {commands}; and not __fish_seen_subcommand_from self\" -f -a \"({bin_name} {complete} {kind})\"\n",
+            bin_name = bin_name,
+            commands = commands.join(" "),
+            complete = COMPLETE_CHANNELS_COMMAND,
+            kind = kind,
+        ));
+    }
+
+    script
+}
+
+fn add_powershell_channel_hook(script: String, bin_name: &str) -> String {
+    let quote = |commands: &[&str]| commands.iter().map(|command| format!("'{}'", command)).collect::<Vec<_>>().join(", ");
+
+    let hook = format!(
+        r#"    $channelKind = switch ($commandElements[1].Extent.Text) {{
+        {{ $_ -in {installed} }} {{ 'installed' }}
+        {{ $_ -in {available} }} {{ 'available' }}
+    }}
+    if ($channelKind -and -not $wordToComplete.StartsWith('-')) {{
+        $completions += @({bin_name} {complete} $channelKind | ForEach-Object {{
+            [CompletionResult]::new($_, $_, [CompletionResultType]::ParameterValue, $_)
+        }})
+    }}
+
+"#,
+        installed = quote(&INSTALLED_CHANNEL_COMMANDS),
+        available = quote(&AVAILABLE_CHANNEL_COMMANDS),
+        bin_name = bin_name,
+        complete = COMPLETE_CHANNELS_COMMAND,
+    );
+
+    match script.find("    $completions.Where{") {
+        Some(position) => {
+            let mut script = script;
+            script.insert_str(position, &hook);
+            script
+        }
+        None => script,
+    }
+}
+
+/// Generates the completion script for `shell` from the definition of the
+/// command line interface in `app`.
+pub fn generate_completions(app: &App, shell: &str) -> Result<String> {
+    let bin_name = "juliaup";
+
+    Ok(match shell {
+        "bash" => add_bash_channel_hook(generate_static::<Bash>(app, bin_name), bin_name),
+        "zsh" => add_zsh_channel_hook(generate_static::<Zsh>(app, bin_name), bin_name),
+        "fish" => add_fish_channel_hook(generate_static::<Fish>(app, bin_name), bin_name),
+        "powershell" => add_powershell_channel_hook(generate_static::<PowerShell>(app, bin_name), bin_name),
+        _ => bail!("Completions for `{}` are not supported.", shell),
+    })
+}
+
+pub fn run_command_completions(app: &App, shell: &str) -> Result<()> {
+    print!("{}", generate_completions(app, shell)?);
+
+    Ok(())
+}
+
+/// Prints one channel name per line for the completion scripts: the
+/// installed channels for `installed`, everything `juliaup add` accepts for
+/// `available`. Problems are ignored, there is nobody to show them to.
+pub fn run_command_complete_channels(kind: &str) -> Result<()> {
+    let mut channels: Vec<String> = match kind {
+        "installed" => match load_config_db() {
+            Ok(config_data) => config_data.installed_channels.keys().cloned().collect(),
+            Err(_) => Vec::new(),
+        },
+        _ => match load_versions_db() {
+            Ok(version_db) => version_db.available_channels.keys().cloned().collect(),
+            Err(_) => Vec::new(),
+        },
+    };
+
+    channels.sort();
+
+    for channel in channels {
+        println!("{}", channel);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+
+    fn test_app() -> App<'static> {
+        App::new("juliaup")
+            .arg(Arg::new("quiet").long("quiet").short('q').global(true))
+            .arg(Arg::new("format").long("format").takes_value(true).possible_values(&["text", "json"]).global(true))
+            .subcommand(App::new("add").about("Add a specific Julia version or channel").arg(Arg::new("channel").required(true)))
+            .subcommand(App::new("remove").about("Don't keep these").arg(Arg::new("channels").multiple(true)))
+            .subcommand(App::new("rename").arg(Arg::new("old-channel").required(true)).arg(Arg::new("new-channel").required(true)))
+            .subcommand(App::new("cache").subcommand(App::new("list")).subcommand(App::new("clean")))
+            .subcommand(App::new("self").subcommand(App::new("update")))
+    }
+
+    /// Runs `shell -n` on `script`, or returns `None` if `shell` isn't
+    /// installed.
+    fn check_syntax(shell: &str, script: &str) -> Option<std::process::Output> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("completions");
+        std::fs::write(&path, script).unwrap();
+
+        std::process::Command::new(shell).arg("-n").arg(&path).output().ok()
+    }
+
+    #[test]
+    fn test_generate_completions_syntax() {
+        for shell in ["bash", "zsh", "fish"] {
+            let script = generate_completions(&test_app(), shell).unwrap();
+
+            if let Some(output) = check_syntax(shell, &script) {
+                assert!(output.status.success(), "{}: {}", shell, String::from_utf8_lossy(&output.stderr));
+            }
+        }
+
+        assert!(generate_completions(&test_app(), "tcsh").is_err());
+    }
+
+    #[test]
+    fn test_generate_completions_channel_hooks() {
+        let zsh = generate_completions(&test_app(), "zsh").unwrap();
+        assert!(zsh.contains("':channel:_juliaup_channels available' \\"));
+        assert!(zsh.contains("'*::channels:_juliaup_channels installed' \\"));
+        assert!(zsh.contains("':old-channel:_juliaup_channels installed' \\"));
+        assert!(zsh.contains("':new-channel:' \\"));
+        assert!(zsh.contains("_juliaup_channels() {"));
+        assert!(zsh.ends_with("_juliaup \"$@\"\n"));
+
+        let fish = generate_completions(&test_app(), "fish").unwrap();
+        assert!(fish.contains("-n \"__fish_seen_subcommand_from add; and not __fish_seen_subcommand_from self\" -f -a \"(juliaup _complete-channels available)\""));
+
+        let powershell = generate_completions(&test_app(), "powershell").unwrap();
+        let hook = powershell.find("$channelKind = switch").unwrap();
+        assert!(hook < powershell.find("$completions.Where{").unwrap());
+        assert!(powershell.contains("{ $_ -in 'add' } { 'available' }"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bash_completions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+
+        // A fake juliaup that only knows `_complete-channels`.
+        let juliaup_path = dir.path().join("juliaup");
+        std::fs::write(
+            &juliaup_path,
+            "#!/bin/sh\ncase \"$2\" in installed) printf 'release\\n1.6\\n' ;; available) printf 'release\\nbeta\\n' ;; esac\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&juliaup_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let script_path = dir.path().join("completions.bash");
+        std::fs::write(&script_path, generate_completions(&test_app(), "bash").unwrap()).unwrap();
+
+        let complete = |words: &str| -> Option<String> {
+            let output = std::process::Command::new("bash")
+                .arg("-c")
+                .arg(format!(
+                    "source \"$0\"; COMP_WORDS=({}); COMP_CWORD=$((${{#COMP_WORDS[@]}} - 1)); _juliaup_channels; echo \"${{COMPREPLY[*]}}\"",
+                    words
+                ))
+                .arg(&script_path)
+                .env("PATH", format!("{}:{}", dir.path().display(), std::env::var("PATH").unwrap_or_default()))
+                .output()
+                .ok()?;
+
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+            Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        };
+
+        if complete("juliaup ''").is_none() {
+            return;
+        }
+
+        assert_eq!(complete("juliaup remove ''").unwrap(), "release 1.6");
+        assert_eq!(complete("juliaup --format json rename r").unwrap(), "release");
+        assert_eq!(complete("juliaup -q add b").unwrap(), "beta");
+        assert_eq!(complete("juliaup add --q").unwrap(), "--quiet");
+        assert_eq!(complete("juliaup ca").unwrap(), "cache");
+        assert_eq!(complete("juliaup cache c").unwrap(), "clean");
+    }
+}
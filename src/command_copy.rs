@@ -0,0 +1,37 @@
+use crate::config_file::{load_config_db, save_config_db, JuliaupConfigChannel};
+use crate::operations::{check_channel_can_move, copy_installed_version};
+use crate::progress::report_info;
+use crate::versions_file::load_versions_db;
+use anyhow::{Context, Result};
+
+pub fn run_command_copy(channel: String, new_channel: String) -> Result<()> {
+    let mut config_data =
+        load_config_db().with_context(|| "`copy` command failed to load configuration file.")?;
+
+    let versiondb_data =
+        load_versions_db().with_context(|| "`copy` command failed to load versions db.")?;
+
+    let channel_data = check_channel_can_move(&config_data, &versiondb_data, &channel, &new_channel, "copied")?;
+
+    let channel_data = match channel_data {
+        // Versions installed from a url are not shared between channels, so
+        // the copy gets its own.
+        JuliaupConfigChannel::DirectDownloadChannel { version, url, sha256 } => {
+            let new_version = format!("url-{}", new_channel);
+
+            copy_installed_version(&version, &new_version, &mut config_data)?;
+
+            JuliaupConfigChannel::DirectDownloadChannel { version: new_version, url, sha256 }
+        }
+        channel_data => channel_data,
+    };
+
+    config_data.installed_channels.insert(new_channel.clone(), channel_data);
+
+    save_config_db(&config_data)
+        .with_context(|| format!("Failed to save configuration file from `copy` command after '{}' was copied.", channel))?;
+
+    report_info(format!("Channel '{}' copied to '{}'.", channel, new_channel));
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use crate::config_file::*;
+use crate::progress::report_info;
 use anyhow::{bail, Context, Result};
 
 pub fn run_command_default(channel: String) -> Result<()> {
@@ -14,7 +15,7 @@ pub fn run_command_default(channel: String) -> Result<()> {
     save_config_db(&config_data)
         .with_context(|| "`default` command failed to save configuration db.")?;
 
-    eprintln!("Configured the default Julia version to be '{}'.", channel);
+    report_info(format!("Configured the default Julia version to be '{}'.", channel));
 
     Ok(())
 }
@@ -0,0 +1,175 @@
+use crate::command_add::run_command_add;
+use crate::config_file::{load_config_db, JuliaupConfigChannel};
+use crate::operations::get_julia_path_from_channel;
+use crate::output::OutputFormat;
+use crate::progress::report_info;
+use crate::utils::get_juliaupconfig_path;
+use anyhow::{anyhow, bail, Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+fn quote_posix(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn quote_fish(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn quote_pwsh(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn get_env_script(bindir: &str, shell: &str) -> Result<String> {
+    Ok(match shell {
+        "bash" => format!(
+            "export PATH={0}\"${{PATH:+:${{PATH}}}}\"\nexport JULIA_BINDIR={0}\n",
+            quote_posix(bindir)
+        ),
+        "fish" => format!("set -gx PATH {0} $PATH\nset -gx JULIA_BINDIR {0}\n", quote_fish(bindir)),
+        "pwsh" => format!(
+            "$env:PATH = {0} + [System.IO.Path]::PathSeparator + $env:PATH\n$env:JULIA_BINDIR = {0}\n",
+            quote_pwsh(bindir)
+        ),
+        _ => bail!("`{}` is not a supported shell.", shell),
+    })
+}
+
+fn get_github_file_path(variable: &str) -> Result<PathBuf> {
+    std::env::var_os(variable)
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("`--shell github` only works in GitHub Actions, `{}` is not set.", variable))
+}
+
+fn append_to_file(path: &Path, line: &str) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open `{}`.", path.display()))?;
+
+    writeln!(file, "{}", line).with_context(|| format!("Failed to write to `{}`.", path.display()))
+}
+
+/// Puts `bindir` on the PATH of the following GitHub Actions steps through
+/// the `GITHUB_PATH` and `GITHUB_ENV` files.
+fn write_github_env(bindir: &str, github_path: &Path, github_env: &Path) -> Result<()> {
+    append_to_file(github_path, bindir)?;
+    append_to_file(github_env, &format!("JULIA_BINDIR={}", bindir))
+}
+
+// Putting the folder on the PATH only makes `julia` start the channel if the
+// command is actually called `julia`.
+fn get_bindir(channel: &str, julia_path: &Path) -> Result<PathBuf> {
+    let bindir = match julia_path.parent() {
+        Some(bindir) if julia_path.is_absolute() => bindir,
+        _ => bail!("'{}' is linked to `{}`, which is not an absolute path.", channel, julia_path.display()),
+    };
+
+    match julia_path.file_name().and_then(|name| name.to_str()) {
+        Some("julia") | Some("julia.exe") => Ok(bindir.to_path_buf()),
+        _ => bail!(
+            "'{}' is linked to `{}`, which is not called `julia`, so putting its folder on the PATH doesn't work.",
+            channel,
+            julia_path.display()
+        ),
+    }
+}
+
+fn get_channel_bindir(channel: &str) -> Result<PathBuf> {
+    let config_data =
+        load_config_db().with_context(|| "`env` command failed to load configuration file.")?;
+
+    if let Some(JuliaupConfigChannel::LinkedChannel { command: _, args: Some(args) }) =
+        config_data.installed_channels.get(channel)
+    {
+        if !args.is_empty() {
+            bail!("'{}' is linked to a command with arguments, which can't be expressed with `PATH`.", channel);
+        }
+    }
+
+    let juliaupconfig_path = get_juliaupconfig_path()
+        .with_context(|| "Failed to retrieve the path of the configuration file in the `env` command.")?;
+
+    let (julia_path, _) = get_julia_path_from_channel(&config_data, channel, &juliaupconfig_path, true)
+        .with_context(|| format!("`env` command failed to determine the command for the `{}` channel.", channel))?;
+
+    get_bindir(channel, &julia_path)
+}
+
+fn path_to_string(path: &Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| anyhow!("The path `{}` is not valid unicode.", path.display()))
+}
+
+pub fn run_command_env(channel: String, shell: Option<String>, install: bool) -> Result<()> {
+    let config_data =
+        load_config_db().with_context(|| "`env` command failed to load configuration file.")?;
+
+    if !config_data.installed_channels.contains_key(&channel) {
+        if !install {
+            bail!("'{}' is not installed. Install it with `juliaup add {}` or pass `--install`.", channel, channel);
+        }
+
+        run_command_add(channel.clone(), false, None, None, None, OutputFormat::Text)
+            .with_context(|| format!("`env` command failed to install '{}'.", channel))?;
+    }
+
+    let bindir = get_channel_bindir(&channel)?;
+    let bindir = path_to_string(&bindir)?;
+
+    let shell = shell.unwrap_or_else(|| if cfg!(windows) { "pwsh" } else { "bash" }.to_string());
+
+    if shell == "github" {
+        write_github_env(bindir, &get_github_file_path("GITHUB_PATH")?, &get_github_file_path("GITHUB_ENV")?)?;
+
+        report_info(format!("Added `{}` to the PATH of the following steps.", bindir));
+    } else {
+        print!("{}", get_env_script(bindir, &shell)?);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_env_script() {
+        assert_eq!(
+            get_env_script("/home/o'neil/julia/bin", "bash").unwrap(),
+            "export PATH='/home/o'\\''neil/julia/bin'\"${PATH:+:${PATH}}\"\nexport JULIA_BINDIR='/home/o'\\''neil/julia/bin'\n"
+        );
+        assert_eq!(
+            get_env_script("C:\\Julia's\\bin", "pwsh").unwrap(),
+            "$env:PATH = 'C:\\Julia''s\\bin' + [System.IO.Path]::PathSeparator + $env:PATH\n$env:JULIA_BINDIR = 'C:\\Julia''s\\bin'\n"
+        );
+        assert!(get_env_script("/julia/bin", "tcsh").is_err());
+    }
+
+    #[test]
+    fn test_get_bindir() {
+        let bindir = std::env::temp_dir().join("julia-1.6.4").join("bin");
+
+        assert_eq!(get_bindir("1.6", &bindir.join("julia")).unwrap(), bindir);
+        assert_eq!(get_bindir("1.6", &bindir.join("julia.exe")).unwrap(), bindir);
+        assert!(get_bindir("dev", &bindir.join("julia-dev")).is_err());
+        assert!(get_bindir("dev", Path::new("julia")).is_err());
+    }
+
+    #[test]
+    fn test_write_github_env() {
+        let dir = tempfile::tempdir().unwrap();
+        let github_path = dir.path().join("path");
+        let github_env = dir.path().join("env");
+        std::fs::write(&github_env, "FOO=bar\n").unwrap();
+
+        write_github_env("/julia/bin", &github_path, &github_env).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&github_path).unwrap(), "/julia/bin\n");
+        assert_eq!(std::fs::read_to_string(&github_env).unwrap(), "FOO=bar\nJULIA_BINDIR=/julia/bin\n");
+
+        assert!(write_github_env("/julia/bin", dir.path(), &github_env).is_err());
+    }
+}
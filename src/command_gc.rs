@@ -1,15 +1,62 @@
-use crate::operations::garbage_collect_versions;
+use crate::operations::{find_orphaned_folders, garbage_collect_versions, get_folder_size, get_unused_versions};
 use crate::config_file::{load_config_db, save_config_db};
+use crate::install_manifest::{list_install_manifests, remove_install_manifest};
+use crate::utils::get_juliaup_home_path;
+use crate::progress::report_info;
 use anyhow::{Context, Result};
+use indicatif::HumanBytes;
 
-pub fn run_command_gc() -> Result<()> {
+pub fn run_command_gc(dry_run: bool) -> Result<()> {
     let mut config_data = load_config_db()
         .with_context(|| "`gc` command failed to load configuration file.")?;
 
-    garbage_collect_versions(&mut config_data)?;
+    let orphaned_folders = find_orphaned_folders(&config_data)?;
+
+    if dry_run {
+        let home_path = get_juliaup_home_path()
+            .with_context(|| "Failed to retrieve juliap folder while trying to garbage collect versions.")?;
+
+        let mut total_size = 0;
+
+        for version in get_unused_versions(&config_data) {
+            let path = home_path.join(&config_data.installed_versions[&version].path);
+            let size = get_folder_size(&path);
+            println!("Would delete unused Julia {} ({}).", version, HumanBytes(size));
+            total_size += size;
+        }
+
+        for path in &orphaned_folders {
+            let size = get_folder_size(path);
+            println!("Would delete orphaned folder `{}` ({}).", path.display(), HumanBytes(size));
+            total_size += size;
+        }
+
+        println!("Running `juliaup gc` would free {}.", HumanBytes(total_size));
+
+        return Ok(());
+    }
+
+    let mut freed_space = garbage_collect_versions(&mut config_data)?;
 
     save_config_db(&config_data)
         .with_context(|| "`gc` command failed to save configuration db.")?;
 
+    for path in orphaned_folders {
+        let size = get_folder_size(&path);
+
+        match std::fs::remove_dir_all(&path) {
+            Ok(()) => freed_space += size,
+            Err(err) => eprintln!("WARNING: Failed to delete orphaned folder {}: {}", path.display(), err),
+        }
+    }
+
+    for version in list_install_manifests()? {
+        if !config_data.installed_versions.contains_key(&version) {
+            remove_install_manifest(&version)?;
+        }
+    }
+
+    report_info(format!("Freed {} of disk space.", HumanBytes(freed_space)));
+
     Ok(())
 }
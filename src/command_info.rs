@@ -0,0 +1,58 @@
+use crate::config_file::load_config_db;
+use crate::output::{print_json, OutputFormat};
+use crate::utils::{get_arch, get_juliaup_cache_path, get_juliaup_home_path, get_juliaupconfig_path};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Serialize)]
+struct Info {
+    #[serde(rename = "JuliaupVersion")]
+    juliaup_version: String,
+    #[serde(rename = "Arch")]
+    arch: String,
+    #[serde(rename = "JuliaupHome")]
+    juliaup_home: PathBuf,
+    #[serde(rename = "ConfigFile")]
+    config_file: PathBuf,
+    #[serde(rename = "CacheFolder")]
+    cache_folder: PathBuf,
+    #[serde(rename = "Default")]
+    default: Option<String>,
+    #[serde(rename = "InstalledChannels")]
+    installed_channels: usize,
+    #[serde(rename = "InstalledVersions")]
+    installed_versions: usize,
+}
+
+pub fn run_command_info(format: OutputFormat) -> Result<()> {
+    let config_data =
+        load_config_db().with_context(|| "`info` command failed to load configuration file.")?;
+
+    let info = Info {
+        juliaup_version: env!("CARGO_PKG_VERSION").to_string(),
+        arch: get_arch()?,
+        juliaup_home: get_juliaup_home_path()?,
+        config_file: get_juliaupconfig_path()?,
+        cache_folder: get_juliaup_cache_path()?,
+        default: config_data.default.clone(),
+        installed_channels: config_data.installed_channels.len(),
+        installed_versions: config_data.installed_versions.len(),
+    };
+
+    match format {
+        OutputFormat::Text => {
+            println!("Juliaup version:     {}", info.juliaup_version);
+            println!("Architecture:        {}", info.arch);
+            println!("Juliaup folder:      {}", info.juliaup_home.display());
+            println!("Configuration file:  {}", info.config_file.display());
+            println!("Download cache:      {}", info.cache_folder.display());
+            println!("Default channel:     {}", info.default.as_deref().unwrap_or("none"));
+            println!("Installed channels:  {}", info.installed_channels);
+            println!("Installed versions:  {}", info.installed_versions);
+        }
+        OutputFormat::Json => print_json(&info)?,
+    }
+
+    Ok(())
+}
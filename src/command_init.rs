@@ -0,0 +1,274 @@
+use crate::command_initial_setup_from_launcher::run_command_initial_setup_from_launcher;
+use crate::command_self::{get_binary_name, get_juliaup_bin_path, JULIAUP_BINARIES};
+use crate::progress::{report_info, report_status};
+use crate::utils::{get_juliaupconfig_path, is_interactive, prompt_confirmation};
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+const PATH_BLOCK_START: &str = "# >>> juliaup initialize >>>";
+const PATH_BLOCK_END: &str = "# <<< juliaup initialize <<<";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Shell {
+    Posix,
+    Fish,
+}
+
+/// The shell startup files that need to put the bin folder on the `PATH`.
+/// These are the files that exist, plus the one of the shell in `$SHELL`.
+fn get_shell_profiles() -> Result<Vec<(PathBuf, Shell)>> {
+    let home_path = dirs::home_dir()
+        .ok_or_else(|| anyhow!("Could not determine the path of the user home directory."))?;
+
+    let zsh_path = std::env::var_os("ZDOTDIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_path.clone());
+
+    let fish_path = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_path.join(".config"))
+        .join("fish");
+
+    let login_shell = std::env::var("SHELL").unwrap_or_default();
+
+    let candidates = vec![
+        (home_path.join(".bashrc"), Shell::Posix, "bash"),
+        (home_path.join(".bash_profile"), Shell::Posix, ""),
+        (zsh_path.join(".zshrc"), Shell::Posix, "zsh"),
+        (fish_path.join("config.fish"), Shell::Fish, "fish"),
+    ];
+
+    Ok(candidates
+        .into_iter()
+        .filter(|(path, _, shell_name)| {
+            path.exists() || (!shell_name.is_empty() && login_shell.ends_with(&format!("/{}", shell_name)))
+        })
+        .map(|(path, shell, _)| (path, shell))
+        .collect())
+}
+
+fn get_path_block(bin_path: &Path, shell: Shell) -> String {
+    let bin_path = bin_path.display().to_string().replace('\'', "'\\''");
+
+    let command = match shell {
+        Shell::Posix => format!(
+            "case \":${{PATH}}:\" in\n    *:'{0}':*) ;;\n    *) export PATH='{0}'${{PATH:+:${{PATH}}}} ;;\nesac",
+            bin_path
+        ),
+        Shell::Fish => format!("if not contains '{0}' $PATH\n    set -gx PATH '{0}' $PATH\nend", bin_path),
+    };
+
+    format!(
+        "{}\n# !! Contents within this block are managed by juliaup !!\n{}\n{}\n",
+        PATH_BLOCK_START, command, PATH_BLOCK_END
+    )
+}
+
+/// Returns `content` without the juliaup block and the block's position.
+fn remove_path_block(content: &str) -> (String, Option<usize>) {
+    match (content.find(PATH_BLOCK_START), content.find(PATH_BLOCK_END)) {
+        (Some(start), Some(end)) if start < end => {
+            let mut end = end + PATH_BLOCK_END.len();
+            if content[end..].starts_with('\n') {
+                end += 1;
+            }
+
+            (format!("{}{}", &content[..start], &content[end..]), Some(start))
+        }
+        _ => (content.to_string(), None),
+    }
+}
+
+/// Adds `block` to `content`, replacing an earlier juliaup block if there
+/// is one, so that running `init` again doesn't add it twice.
+fn insert_path_block(content: &str, block: &str) -> String {
+    match remove_path_block(content) {
+        (mut content, Some(position)) => {
+            content.insert_str(position, block);
+            content
+        }
+        (mut content, None) => {
+            if !content.is_empty() && !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push_str(block);
+            content
+        }
+    }
+}
+
+fn add_to_shell_profiles(bin_path: &Path) -> Result<()> {
+    for (profile_path, shell) in get_shell_profiles()? {
+        let content = match std::fs::read_to_string(&profile_path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read `{}`.", profile_path.display()))
+            }
+        };
+
+        let new_content = insert_path_block(&content, &get_path_block(bin_path, shell));
+
+        if new_content != content {
+            if let Some(parent) = profile_path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create folder `{}`.", parent.display()))?;
+            }
+
+            std::fs::write(&profile_path, new_content)
+                .with_context(|| format!("Failed to update `{}`.", profile_path.display()))?;
+
+            report_status("Updated", profile_path.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the blocks that `juliaup init` added to the shell startup files.
+pub fn remove_from_shell_profiles() -> Result<()> {
+    for (profile_path, _) in get_shell_profiles()? {
+        let content = match std::fs::read_to_string(&profile_path) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+
+        if let (new_content, Some(_)) = remove_path_block(&content) {
+            std::fs::write(&profile_path, new_content)
+                .with_context(|| format!("Failed to update `{}`.", profile_path.display()))?;
+
+            report_status("Updated", profile_path.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn get_default_bin_path() -> Result<PathBuf> {
+    let home_path = dirs::home_dir()
+        .ok_or_else(|| anyhow!("Could not determine the path of the user home directory."))?;
+
+    Ok(home_path.join(".juliaup").join("bin"))
+}
+
+// Copies via a temporary file so that a running copy of the target is never
+// overwritten in place.
+fn install_binary(source: &Path, target: &Path) -> Result<()> {
+    let temp_target = target.with_extension("new");
+
+    std::fs::copy(source, &temp_target)
+        .with_context(|| format!("Failed to copy `{}` to `{}`.", source.display(), temp_target.display()))?;
+
+    std::fs::rename(&temp_target, target)
+        .with_context(|| format!("Failed to move `{}` to `{}`.", temp_target.display(), target.display()))?;
+
+    Ok(())
+}
+
+/// Creates the `julia` command in `bin_path`, which starts the launcher next
+/// to it. Where it is a copy of the launcher it has to be created again
+/// whenever the launcher changes.
+pub fn create_julia_shim(bin_path: &Path) -> Result<()> {
+    let shim_path = bin_path.join(get_binary_name("julia"));
+
+    if std::fs::symlink_metadata(&shim_path).is_ok() {
+        std::fs::remove_file(&shim_path)
+            .with_context(|| format!("Failed to remove the old `{}`.", shim_path.display()))?;
+    }
+
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(get_binary_name("julialauncher"), &shim_path)
+        .with_context(|| format!("Failed to create `{}`.", shim_path.display()))?;
+
+    // Symlinks need special privileges on Windows, a copy of the launcher
+    // works just as well there.
+    #[cfg(not(unix))]
+    std::fs::copy(bin_path.join(get_binary_name("julialauncher")), &shim_path)
+        .with_context(|| format!("Failed to create `{}`.", shim_path.display()))?;
+
+    Ok(())
+}
+
+pub fn run_command_init(bin_dir: Option<PathBuf>, no_modify_path: bool, yes: bool) -> Result<()> {
+    let source_path = get_juliaup_bin_path()?;
+
+    let bin_path = match bin_dir {
+        Some(bin_dir) => std::env::current_dir()?.join(bin_dir),
+        None => get_default_bin_path()?,
+    };
+
+    let modify_path = !no_modify_path && cfg!(unix);
+
+    if !yes && is_interactive() {
+        report_info("This will");
+        report_info(format!("  - install juliaup into `{}`", bin_path.display()));
+        report_info("  - create the `julia` command in that folder");
+        if modify_path {
+            report_info("  - add that folder to the PATH in your shell startup files");
+        }
+        report_info("  - install the `release` channel of Julia if juliaup is not set up yet");
+
+        if !prompt_confirmation("Continue?")? {
+            bail!("Setup cancelled.");
+        }
+    }
+
+    std::fs::create_dir_all(&bin_path)
+        .with_context(|| format!("Failed to create folder `{}`.", bin_path.display()))?;
+
+    let bin_path = bin_path
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve `{}`.", bin_path.display()))?;
+
+    if bin_path != source_path.canonicalize()? {
+        for name in JULIAUP_BINARIES {
+            install_binary(&source_path.join(get_binary_name(name)), &bin_path.join(get_binary_name(name)))?;
+        }
+
+        report_status("Installed", format!("juliaup into {}", bin_path.display()));
+    }
+
+    create_julia_shim(&bin_path)?;
+
+    if modify_path {
+        add_to_shell_profiles(&bin_path)?;
+    } else if !no_modify_path {
+        report_info(format!("Please add `{}` to your PATH.", bin_path.display()));
+    }
+
+    if !get_juliaupconfig_path()?.exists() {
+        run_command_initial_setup_from_launcher()
+            .with_context(|| "Failed to install the initial Julia version.")?;
+    }
+
+    if modify_path {
+        report_info("juliaup is set up, restart your shell to use the `julia` command.");
+    } else {
+        report_info("juliaup is set up.");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_path_block() {
+        let block = get_path_block(Path::new("/home/user/.juliaup/bin"), Shell::Posix);
+
+        let content = insert_path_block("alias ll='ls -l'", &block);
+        assert_eq!(content, format!("alias ll='ls -l'\n{}", block));
+
+        // Running it again replaces the old block in place.
+        let content = format!("{}export EDITOR=vim\n", content);
+        let new_block = get_path_block(Path::new("/opt/juliaup/bin"), Shell::Posix);
+        assert_eq!(
+            insert_path_block(&content, &new_block),
+            format!("alias ll='ls -l'\n{}export EDITOR=vim\n", new_block)
+        );
+
+        assert_eq!(remove_path_block(&content), ("alias ll='ls -l'\nexport EDITOR=vim\n".to_string(), Some(17)));
+    }
+}
@@ -1,7 +1,7 @@
 use crate::operations::install_version;
 use crate::versions_file::load_versions_db;
 use crate::config_file::save_config_db;
-use crate::config_file::{JuliaupConfigVersion,JuliaupConfigChannel};
+use crate::config_file::{JuliaupConfigVersion,JuliaupConfigChannel,JuliaupConfigSettings};
 use std::collections::HashMap;
 use crate::config_file::JuliaupConfig;
 use crate::utils::get_juliaup_home_path;
@@ -39,6 +39,7 @@ pub fn run_command_initial_setup_from_launcher() -> Result<()> {
             default: Some("release".to_string()),
             installed_versions: HashMap::new(),
             installed_channels: HashMap::new(),
+            settings: JuliaupConfigSettings::default(),
         };
 
         juliaup_confi_data.installed_versions.insert(
@@ -48,6 +49,7 @@ pub fn run_command_initial_setup_from_launcher() -> Result<()> {
                     .join(&target_folder_name)
                     .display()
                     .to_string(),
+                from_file: None,
             },
         );
 
@@ -63,6 +65,7 @@ pub fn run_command_initial_setup_from_launcher() -> Result<()> {
             default: Some("release".to_string()),
             installed_versions: HashMap::new(),
             installed_channels: HashMap::new(),
+            settings: JuliaupConfigSettings::default(),
         };
 
         juliaup_confi_data.installed_channels.insert(
@@ -77,7 +80,7 @@ pub fn run_command_initial_setup_from_launcher() -> Result<()> {
 
         std::fs::create_dir_all(juliaup_folder)?;
 
-        install_version(&full_version_string, &mut juliaup_confi_data, &version_db)?;
+        install_version(&full_version_string, &mut juliaup_confi_data, &version_db, true)?;
 
         save_config_db(&juliaup_confi_data)?;
     }
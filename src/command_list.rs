@@ -0,0 +1,176 @@
+use crate::config_file::load_config_db;
+use crate::output::{print_json, OutputFormat};
+use crate::utils::parse_versionstring;
+use crate::versions_file::load_versions_db;
+use anyhow::{Context, Result};
+use semver::{Prerelease, Version, VersionReq};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Serialize)]
+struct ListChannel {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Version")]
+    version: String,
+}
+
+#[derive(Serialize)]
+struct ListVersion {
+    #[serde(rename = "Version")]
+    version: String,
+    #[serde(rename = "Arch")]
+    arch: String,
+    #[serde(rename = "Installed")]
+    installed: bool,
+}
+
+#[derive(Serialize)]
+struct List {
+    #[serde(rename = "Channels")]
+    channels: Vec<ListChannel>,
+    #[serde(rename = "Versions")]
+    versions: Vec<ListVersion>,
+}
+
+// Prereleases are matched against `range` as if they were the release they
+// lead up to, otherwise `semver` would only match them for ranges that
+// mention that exact prerelease.
+fn matches_range(version: &Version, range: &Option<VersionReq>) -> bool {
+    match range {
+        Some(range) => {
+            let mut release = version.clone();
+            release.pre = Prerelease::EMPTY;
+            range.matches(&release)
+        }
+        None => true,
+    }
+}
+
+fn print_list_text(list: &List) {
+    println!("Available channels:");
+
+    let name_width = list.channels.iter().map(|channel| channel.name.len()).max().unwrap_or(0);
+    for channel in &list.channels {
+        println!("  {:width$}  {}", channel.name, channel.version, width = name_width);
+    }
+
+    println!();
+    println!("Available Julia versions (installed marked with *):");
+
+    // Groups the archs of each version by major/minor, newest first.
+    let mut groups: BTreeMap<(u64, u64), BTreeMap<Version, Vec<String>>> = BTreeMap::new();
+    for entry in &list.versions {
+        if let Ok(version) = Version::parse(&entry.version) {
+            let arch = if entry.installed { format!("{}*", entry.arch) } else { entry.arch.clone() };
+
+            groups
+                .entry((version.major, version.minor))
+                .or_default()
+                .entry(version)
+                .or_default()
+                .push(arch);
+        }
+    }
+
+    for ((major, minor), versions) in groups.iter().rev() {
+        println!("  {}.{}:", major, minor);
+
+        let version_width = versions.keys().map(|version| version.to_string().len()).max().unwrap_or(0);
+        for (version, archs) in versions.iter().rev() {
+            println!("    {:width$}  {}", version.to_string(), archs.join(", "), width = version_width);
+        }
+    }
+}
+
+pub fn run_command_list(
+    prerelease: bool,
+    arch: Option<String>,
+    range: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    let version_db =
+        load_versions_db().with_context(|| "`list` command failed to load versions db.")?;
+
+    let config_data =
+        load_config_db().with_context(|| "`list` command failed to load configuration file.")?;
+
+    let range = match range {
+        Some(range) => Some(VersionReq::parse(&range).with_context(|| {
+            format!("'{}' is not a valid version range, try something like `>=1.5, <1.7` or `~1.6`.", range)
+        })?),
+        None => None,
+    };
+
+    // Channels like `1.6` or `1.6.2~x86` just name a version, so we only show
+    // the named ones like `release` here.
+    let mut channels: Vec<ListChannel> = version_db
+        .available_channels
+        .iter()
+        .filter(|(name, _)| !name.starts_with(|c: char| c.is_ascii_digit()) && !name.contains('~'))
+        .map(|(name, channel)| ListChannel {
+            name: name.clone(),
+            version: channel.version.clone(),
+        })
+        .collect();
+
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut versions: Vec<(Version, ListVersion)> = Vec::new();
+
+    for fullversion in version_db.available_versions.keys() {
+        let (platform, version) = parse_versionstring(fullversion)?;
+
+        if (!prerelease && !version.pre.is_empty())
+            || arch.as_ref().is_some_and(|arch| arch != &platform)
+            || !matches_range(&version, &range)
+        {
+            continue;
+        }
+
+        let mut display_version = version.clone();
+        display_version.build = semver::BuildMetadata::EMPTY;
+
+        versions.push((
+            version,
+            ListVersion {
+                version: display_version.to_string(),
+                arch: platform,
+                installed: config_data.installed_versions.contains_key(fullversion),
+            },
+        ));
+    }
+
+    versions.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.arch.cmp(&b.1.arch)));
+
+    let list = List {
+        channels,
+        versions: versions.into_iter().map(|(_, version)| version).collect(),
+    };
+
+    match format {
+        OutputFormat::Text => print_list_text(&list),
+        OutputFormat::Json => print_json(&list)?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_range() {
+        let range = Some(VersionReq::parse(">=1.5, <1.7").unwrap());
+
+        assert!(matches_range(&Version::parse("1.6.2").unwrap(), &range));
+        assert!(matches_range(&Version::parse("1.5.0").unwrap(), &range));
+        assert!(!matches_range(&Version::parse("1.7.0-beta3").unwrap(), &range));
+        assert!(!matches_range(&Version::parse("1.4.2").unwrap(), &range));
+
+        let range = Some(VersionReq::parse("~1.7").unwrap());
+        assert!(matches_range(&Version::parse("1.7.0-beta3").unwrap(), &range));
+        assert!(matches_range(&Version::parse("1.0.5").unwrap(), &None));
+    }
+}
@@ -0,0 +1,314 @@
+use crate::command_run::get_exit_code;
+use crate::config_file::load_config_db;
+use crate::operations::get_julia_path_from_channel;
+use crate::output::{print_json, OutputFormat};
+use crate::progress::report_status;
+use crate::utils::get_juliaupconfig_path;
+use anyhow::{bail, Context, Result};
+use console::style;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+struct MatrixJob {
+    channel: String,
+    julia_path: PathBuf,
+    julia_args: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct MatrixResult {
+    #[serde(rename = "Channel")]
+    channel: String,
+    #[serde(rename = "Passed")]
+    passed: bool,
+    #[serde(rename = "ExitCode")]
+    exit_code: Option<i32>,
+    #[serde(rename = "Error")]
+    error: Option<String>,
+    #[serde(rename = "Seconds")]
+    seconds: f64,
+    #[serde(rename = "Stdout")]
+    stdout: String,
+    #[serde(rename = "Stderr")]
+    stderr: String,
+}
+
+#[derive(Serialize)]
+struct Matrix {
+    #[serde(rename = "Channels")]
+    channels: Vec<MatrixResult>,
+}
+
+fn failed_matrix_result(channel: &str, error: String, seconds: f64) -> MatrixResult {
+    MatrixResult {
+        channel: channel.to_string(),
+        passed: false,
+        exit_code: None,
+        error: Some(error),
+        seconds,
+        stdout: String::new(),
+        stderr: String::new(),
+    }
+}
+
+fn run_matrix_job(job: &MatrixJob, args: &[String]) -> MatrixResult {
+    let start = Instant::now();
+
+    let output = std::process::Command::new(&job.julia_path)
+        .args(&job.julia_args)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output();
+
+    let seconds = start.elapsed().as_secs_f64();
+
+    match output {
+        Ok(output) => MatrixResult {
+            channel: job.channel.clone(),
+            passed: output.status.success(),
+            exit_code: Some(get_exit_code(output.status)),
+            error: None,
+            seconds,
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        },
+        Err(err) => failed_matrix_result(
+            &job.channel,
+            format!("Failed to start `{}`: {}", job.julia_path.display(), err),
+            seconds,
+        ),
+    }
+}
+
+// Linked channels can have any name, including `/` or `..`, so only the
+// characters that are safe in a file name are kept.
+fn get_log_filename(channel: &str) -> String {
+    let name: String = channel
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+' | '~') { c } else { '_' })
+        .collect();
+
+    format!("{}.log", name)
+}
+
+fn save_matrix_log(log_dir: &Path, result: &MatrixResult) -> Result<()> {
+    let log_path = log_dir.join(get_log_filename(&result.channel));
+
+    std::fs::write(&log_path, format!("{}{}", result.stdout, result.stderr))
+        .with_context(|| format!("Failed to write the output of '{}' to `{}`.", result.channel, log_path.display()))
+}
+
+fn print_matrix_output(result: &MatrixResult) {
+    println!("{}", style(format!("==> {}", result.channel)).bold());
+    print!("{}", result.stdout);
+    print!("{}", result.stderr);
+
+    if let Some(error) = &result.error {
+        println!("{}", error);
+    }
+
+    println!();
+}
+
+fn print_matrix_table(results: &[MatrixResult]) {
+    let name_width = results.iter().map(|result| result.channel.len()).max().unwrap_or(0).max("Channel".len());
+
+    println!("{:width$}  Result  Exit code  Time", "Channel", width = name_width);
+
+    for result in results {
+        let outcome = if result.passed {
+            style("pass").green().bold()
+        } else {
+            style("FAIL").red().bold()
+        };
+
+        let exit_code = result.exit_code.map_or_else(|| "-".to_string(), |code| code.to_string());
+
+        println!(
+            "{:width$}  {}    {:9}  {:.1}s",
+            result.channel,
+            outcome,
+            exit_code,
+            result.seconds,
+            width = name_width
+        );
+    }
+}
+
+/// Runs Julia with `args` once for each of `channels`, either one after the
+/// other or all at the same time, and reports which channels passed.
+pub fn run_command_matrix(
+    channels: Vec<String>,
+    args: Vec<String>,
+    parallel: bool,
+    log_dir: Option<PathBuf>,
+    format: OutputFormat,
+) -> Result<()> {
+    let config_data =
+        load_config_db().with_context(|| "`matrix` command failed to load configuration file.")?;
+
+    let juliaupconfig_path = get_juliaupconfig_path()
+        .with_context(|| "Failed to retrieve the path of the configuration file in the `matrix` command.")?;
+
+    let not_installed: Vec<&str> = channels
+        .iter()
+        .filter(|channel| !config_data.installed_channels.contains_key(*channel))
+        .map(|channel| channel.as_str())
+        .collect();
+
+    if !not_installed.is_empty() {
+        bail!("The following channels are not installed: {}.", not_installed.join(", "));
+    }
+
+    let jobs = channels
+        .iter()
+        .map(|channel| {
+            let (julia_path, julia_args) =
+                get_julia_path_from_channel(&config_data, channel, &juliaupconfig_path, true).with_context(|| {
+                    format!("`matrix` command failed to determine the command for the `{}` channel.", channel)
+                })?;
+
+            Ok(MatrixJob {
+                channel: channel.clone(),
+                julia_path,
+                julia_args,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(log_dir) = &log_dir {
+        std::fs::create_dir_all(log_dir)
+            .with_context(|| format!("Failed to create the log folder `{}`.", log_dir.display()))?;
+    }
+
+    let finish_job = |result: &MatrixResult| -> Result<()> {
+        if let Some(log_dir) = &log_dir {
+            save_matrix_log(log_dir, result)?;
+        }
+
+        if format == OutputFormat::Text {
+            print_matrix_output(result);
+        }
+
+        Ok(())
+    };
+
+    let mut results = Vec::new();
+
+    if parallel {
+        report_status("Running", format!("Julia on {} channels", jobs.len()));
+
+        let parallel_results: Vec<MatrixResult> = std::thread::scope(|scope| {
+            let handles: Vec<_> = jobs
+                .iter()
+                .map(|job| {
+                    let args = &args;
+                    scope.spawn(move || run_matrix_job(job, args))
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .zip(&jobs)
+                .map(|(handle, job)| {
+                    handle.join().unwrap_or_else(|_| {
+                        failed_matrix_result(&job.channel, "The thread that ran Julia panicked.".to_string(), 0.0)
+                    })
+                })
+                .collect()
+        });
+
+        for result in parallel_results {
+            finish_job(&result)?;
+            results.push(result);
+        }
+    } else {
+        for job in &jobs {
+            report_status("Running", format!("Julia {}", job.channel));
+
+            let result = run_matrix_job(job, &args);
+            finish_job(&result)?;
+            results.push(result);
+        }
+    }
+
+    let failed_channels: Vec<String> = results
+        .iter()
+        .filter(|result| !result.passed)
+        .map(|result| result.channel.clone())
+        .collect();
+
+    match format {
+        OutputFormat::Text => print_matrix_table(&results),
+        OutputFormat::Json => print_json(&Matrix { channels: results })?,
+    }
+
+    if !failed_channels.is_empty() {
+        bail!("Julia failed on {} of {} channels: {}.", failed_channels.len(), jobs.len(), failed_channels.join(", "));
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn fake_julia_job(dir: &Path, channel: &str, script: &str) -> MatrixJob {
+        let julia_path = dir.join(get_log_filename(channel));
+        std::fs::write(&julia_path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&julia_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        MatrixJob {
+            channel: channel.to_string(),
+            julia_path,
+            julia_args: vec!["--startup-file=no".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_run_matrix_job() {
+        let dir = tempfile::tempdir().unwrap();
+        let args = vec!["-e".to_string(), "1".to_string()];
+
+        let job = fake_julia_job(dir.path(), "release", "echo \"$@\"; echo warning >&2");
+        let result = run_matrix_job(&job, &args);
+        assert!(result.passed);
+        assert_eq!(result.exit_code, Some(0));
+        assert_eq!(result.stdout, "--startup-file=no -e 1\n");
+        assert_eq!(result.stderr, "warning\n");
+
+        let job = fake_julia_job(dir.path(), "1.6", "exit 3");
+        let result = run_matrix_job(&job, &args);
+        assert!(!result.passed);
+        assert_eq!(result.exit_code, Some(3));
+
+        let job = MatrixJob {
+            channel: "dev".to_string(),
+            julia_path: dir.path().join("does-not-exist"),
+            julia_args: Vec::new(),
+        };
+        let result = run_matrix_job(&job, &args);
+        assert!(!result.passed);
+        assert_eq!(result.exit_code, None);
+        assert!(result.error.unwrap().contains("does-not-exist"));
+    }
+
+    #[test]
+    fn test_save_matrix_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_dir = dir.path().join("logs");
+        std::fs::create_dir(&log_dir).unwrap();
+
+        let mut result = failed_matrix_result("../dev/1.6~x86", "error".to_string(), 1.0);
+        result.stdout = "out\n".to_string();
+        result.stderr = "err\n".to_string();
+
+        save_matrix_log(&log_dir, &result).unwrap();
+
+        assert_eq!(std::fs::read_to_string(log_dir.join(".._dev_1.6~x86.log")).unwrap(), "out\nerr\n");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -1,29 +1,169 @@
-use crate::operations::garbage_collect_versions;
+use crate::operations::{garbage_collect_versions, get_unused_versions};
 use crate::config_file::*;
+use crate::output::{print_json, OutputFormat};
+use crate::progress::report_info;
+use crate::utils::{is_interactive, parse_versionstring, prompt_confirmation};
 use anyhow::{bail, Context, Result};
+use serde::Serialize;
 
-pub fn run_command_remove(channel: String) -> Result<()> {
+#[derive(Serialize)]
+struct RemoveResult {
+    #[serde(rename = "Channels")]
+    channels: Vec<String>,
+    #[serde(rename = "Default")]
+    default: Option<String>,
+    #[serde(rename = "RemovedVersions")]
+    removed_versions: Vec<String>,
+    #[serde(rename = "FreedBytes")]
+    freed_bytes: u64,
+}
+
+/// Picks the channel that becomes the default after the current default was
+/// removed: `release` if it is installed, otherwise the channel with the
+/// newest Julia version, otherwise the first channel by name.
+fn pick_new_default(config_data: &JuliaupConfig) -> Option<String> {
+    if config_data.installed_channels.contains_key("release") {
+        return Some("release".to_string());
+    }
+
+    let mut channels: Vec<&String> = config_data.installed_channels.keys().collect();
+    channels.sort();
+
+    let newest = channels
+        .iter()
+        .filter_map(|channel| match &config_data.installed_channels[*channel] {
+            JuliaupConfigChannel::SystemChannel { version } => {
+                parse_versionstring(version).ok().map(|(_, version)| (version, *channel))
+            }
+            _ => None,
+        })
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, channel)| channel.clone());
+
+    newest.or_else(|| channels.first().map(|channel| channel.to_string()))
+}
+
+pub fn run_command_remove(channels: Vec<String>, all_except: Option<String>, force: bool, format: OutputFormat) -> Result<()> {
     let mut config_data =
         load_config_db().with_context(|| "`remove` command failed to load configuration file.")?;
 
-    if !config_data.installed_channels.contains_key(&channel) {
-        bail!("'{}' cannot be removed because it is currently not installed.", channel);
+    let mut channels = match all_except {
+        Some(keep) => {
+            if !config_data.installed_channels.contains_key(&keep) {
+                bail!("'{}' is currently not installed.", keep);
+            }
+
+            config_data.installed_channels.keys().filter(|channel| **channel != keep).cloned().collect()
+        }
+        None => channels,
+    };
+
+    channels.sort();
+    channels.dedup();
+
+    for channel in &channels {
+        if !config_data.installed_channels.contains_key(channel) {
+            bail!("'{}' cannot be removed because it is currently not installed.", channel);
+        }
+    }
+
+    if channels.is_empty() {
+        report_info("There are no channels to remove.");
+        return Ok(());
     }
 
-    if let Some(ref default_value) = config_data.default {
-        if &channel==default_value {
-            bail!("'{}' cannot be removed because it is currently configured as the default channel.", channel);
+    for channel in &channels {
+        config_data.installed_channels.remove(channel);
+    }
+
+    if let Some(default_value) = config_data.default.clone() {
+        if channels.contains(&default_value) {
+            let new_default = pick_new_default(&config_data);
+
+            let description = match &new_default {
+                Some(new_default) => format!("'{}' becomes the new default", new_default),
+                None => "there will be no default channel".to_string(),
+            };
+
+            if !force {
+                if !is_interactive() {
+                    bail!("'{}' cannot be removed because it is currently configured as the default channel, pass `--force` to remove it anyway.", default_value);
+                }
+
+                if !prompt_confirmation(&format!("'{}' is the default channel, if you remove it {}. Continue?", default_value, description))? {
+                    bail!("'{}' was not removed because it is currently configured as the default channel.", default_value);
+                }
+            }
+
+            match &new_default {
+                Some(new_default) => report_info(format!("'{}' is now the default channel.", new_default)),
+                None => report_info("There is no default channel anymore."),
+            }
+
+            config_data.default = new_default;
         }
     }
 
-    config_data.installed_channels.remove(&channel);
+    let mut removed_versions = get_unused_versions(&config_data);
+    removed_versions.sort();
 
-    garbage_collect_versions(&mut config_data)?;
+    let freed_bytes = garbage_collect_versions(&mut config_data)?;
 
     save_config_db(&config_data)
-        .with_context(|| format!("Failed to save configuration file from `remove` command after '{}' was installed.", channel))?;
+        .with_context(|| format!("Failed to save configuration file from `remove` command after '{}' was removed.", channels.join("', '")))?;
+
+    for channel in &channels {
+        report_info(format!("Julia '{}' successfully removed.", channel));
+    }
 
-    eprintln!("Julia '{}' successfully removed.", channel);
+    if format == OutputFormat::Json {
+        print_json(&RemoveResult {
+            channels,
+            default: config_data.default.clone(),
+            removed_versions,
+            freed_bytes,
+        })?;
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config_with_channels(channels: &[(&str, JuliaupConfigChannel)]) -> JuliaupConfig {
+        JuliaupConfig {
+            default: None,
+            installed_versions: HashMap::new(),
+            installed_channels: channels.iter().map(|(name, channel)| (name.to_string(), channel.clone())).collect(),
+            settings: JuliaupConfigSettings::default(),
+        }
+    }
+
+    fn system_channel(version: &str) -> JuliaupConfigChannel {
+        JuliaupConfigChannel::SystemChannel { version: version.to_string() }
+    }
+
+    #[test]
+    fn test_pick_new_default() {
+        let linked = JuliaupConfigChannel::LinkedChannel { command: "julia".to_string(), args: None };
+
+        let config_data = config_with_channels(&[("1.6", system_channel("1.6.2+0~x64")), ("release", system_channel("1.6.2+0~x64"))]);
+        assert_eq!(pick_new_default(&config_data), Some("release".to_string()));
+
+        let config_data = config_with_channels(&[
+            ("1.6", system_channel("1.6.2+0~x64")),
+            ("beta", system_channel("1.7.0-beta3+0~x64")),
+            ("1.0", system_channel("1.0.5+0~x86")),
+            ("dev", linked.clone()),
+        ]);
+        assert_eq!(pick_new_default(&config_data), Some("beta".to_string()));
+
+        let config_data = config_with_channels(&[("master", linked.clone()), ("dev", linked)]);
+        assert_eq!(pick_new_default(&config_data), Some("dev".to_string()));
+
+        assert_eq!(pick_new_default(&config_with_channels(&[])), None);
+    }
+}
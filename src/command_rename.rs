@@ -0,0 +1,162 @@
+use crate::config_file::{load_config_db, save_config_db, JuliaupConfig, JuliaupConfigChannel};
+use crate::jsonstructs_versionsdb::JuliaupVersionDB;
+use crate::operations::{check_channel_can_move, rename_installed_version};
+use crate::progress::report_info;
+use crate::versions_file::load_versions_db;
+use anyhow::{Context, Result};
+
+/// Renames the installed `old_channel` to `new_channel` in `config_data`,
+/// along with the default and the version of a channel installed from a url,
+/// and saves the configuration file.
+fn rename_channel(config_data: &mut JuliaupConfig, version_db: &JuliaupVersionDB, old_channel: &str, new_channel: &str) -> Result<()> {
+    let channel_data = check_channel_can_move(config_data, version_db, old_channel, new_channel, "renamed")?;
+
+    let mut moved_version = None;
+
+    let channel_data = match channel_data {
+        // The version of a channel installed from a url is named after the
+        // channel, so it moves along.
+        JuliaupConfigChannel::DirectDownloadChannel { version, url, sha256 } => {
+            let new_version = format!("url-{}", new_channel);
+
+            rename_installed_version(&version, &new_version, config_data)?;
+            moved_version = Some((version, new_version.clone()));
+
+            JuliaupConfigChannel::DirectDownloadChannel { version: new_version, url, sha256 }
+        }
+        channel_data => channel_data,
+    };
+
+    config_data.installed_channels.remove(old_channel);
+    config_data.installed_channels.insert(new_channel.to_string(), channel_data);
+
+    if config_data.default.as_deref() == Some(old_channel) {
+        config_data.default = Some(new_channel.to_string());
+    }
+
+    if let Err(err) = save_config_db(config_data) {
+        // The configuration file still points to the old folder.
+        if let Some((version, new_version)) = moved_version {
+            if let Err(rollback_err) = rename_installed_version(&new_version, &version, config_data) {
+                eprintln!("WARNING: Failed to move Julia {} back to {}: {:#}", new_version, version, rollback_err);
+            }
+        }
+
+        return Err(err).with_context(|| format!("Failed to save configuration file from `rename` command after '{}' was renamed.", old_channel));
+    }
+
+    Ok(())
+}
+
+pub fn run_command_rename(old_channel: String, new_channel: String) -> Result<()> {
+    let mut config_data =
+        load_config_db().with_context(|| "`rename` command failed to load configuration file.")?;
+
+    let versiondb_data =
+        load_versions_db().with_context(|| "`rename` command failed to load versions db.")?;
+
+    rename_channel(&mut config_data, &versiondb_data, &old_channel, &new_channel)?;
+
+    report_info(format!("Channel '{}' renamed to '{}'.", old_channel, new_channel));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_file::{JuliaupConfigSettings, JuliaupConfigVersion};
+    use crate::install_manifest::{load_install_manifest, save_install_manifest, InstallManifest};
+    use crate::utils::{get_juliaupconfig_path, use_test_juliaup_home};
+    use std::collections::{BTreeMap, HashMap};
+    use std::path::Path;
+
+    // A config where `nightly` is the default and is installed from a url.
+    fn url_channel_config(home_path: &Path) -> JuliaupConfig {
+        std::fs::create_dir_all(home_path.join("julia-url-nightly").join("bin")).unwrap();
+        save_install_manifest("url-nightly", &InstallManifest { files: BTreeMap::new() }).unwrap();
+
+        let mut installed_versions = HashMap::new();
+        installed_versions.insert(
+            "url-nightly".to_string(),
+            JuliaupConfigVersion { path: "./julia-url-nightly".to_string(), from_file: None },
+        );
+
+        let mut installed_channels = HashMap::new();
+        installed_channels.insert(
+            "nightly".to_string(),
+            JuliaupConfigChannel::DirectDownloadChannel {
+                version: "url-nightly".to_string(),
+                url: "https://example.com/julia.tar.gz".to_string(),
+                sha256: None,
+            },
+        );
+        installed_channels.insert("release".to_string(), JuliaupConfigChannel::SystemChannel { version: "1.6.4+0~x64".to_string() });
+
+        JuliaupConfig {
+            default: Some("nightly".to_string()),
+            installed_versions,
+            installed_channels,
+            settings: JuliaupConfigSettings::default(),
+        }
+    }
+
+    fn empty_version_db() -> JuliaupVersionDB {
+        JuliaupVersionDB { available_versions: HashMap::new(), available_channels: HashMap::new() }
+    }
+
+    #[test]
+    fn test_rename_channel() {
+        let home = use_test_juliaup_home();
+        let mut config_data = url_channel_config(home.path());
+
+        rename_channel(&mut config_data, &empty_version_db(), "nightly", "mine").unwrap();
+
+        assert_eq!(config_data.default.as_deref(), Some("mine"));
+        assert!(!config_data.installed_channels.contains_key("nightly"));
+        match &config_data.installed_channels["mine"] {
+            JuliaupConfigChannel::DirectDownloadChannel { version, .. } => assert_eq!(version, "url-mine"),
+            _ => panic!("'mine' is not a channel installed from a url."),
+        }
+
+        assert!(!config_data.installed_versions.contains_key("url-nightly"));
+        assert!(home.path().join(&config_data.installed_versions["url-mine"].path).join("bin").is_dir());
+        assert!(!home.path().join("julia-url-nightly").exists());
+        assert!(load_install_manifest("url-mine").unwrap().is_some());
+        assert!(load_install_manifest("url-nightly").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rename_channel_checks_names() {
+        let home = use_test_juliaup_home();
+        let mut config_data = url_channel_config(home.path());
+        let version_db = empty_version_db();
+
+        // The new name ends up in a folder name, so it must not leave the
+        // juliaup folder.
+        assert!(rename_channel(&mut config_data, &version_db, "nightly", "../escape").is_err());
+        assert!(rename_channel(&mut config_data, &version_db, "nightly", "release").is_err());
+        assert!(rename_channel(&mut config_data, &version_db, "release", "stable").is_err());
+
+        assert!(home.path().join("julia-url-nightly").join("bin").is_dir());
+        assert!(config_data.installed_channels.contains_key("nightly"));
+        assert!(config_data.installed_channels.contains_key("release"));
+    }
+
+    // When the configuration file can't be saved, the folder and the manifest
+    // go back to where the old configuration file expects them.
+    #[test]
+    fn test_rename_channel_rolls_back() {
+        let home = use_test_juliaup_home();
+        let mut config_data = url_channel_config(home.path());
+
+        std::fs::create_dir(get_juliaupconfig_path().unwrap()).unwrap();
+
+        assert!(rename_channel(&mut config_data, &empty_version_db(), "nightly", "mine").is_err());
+
+        assert!(home.path().join("julia-url-nightly").join("bin").is_dir());
+        assert!(!home.path().join("julia-url-mine").exists());
+        assert!(load_install_manifest("url-nightly").unwrap().is_some());
+        assert!(load_install_manifest("url-mine").unwrap().is_none());
+    }
+}
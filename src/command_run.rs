@@ -0,0 +1,68 @@
+use crate::command_add::run_command_add;
+use crate::config_file::load_config_db;
+use crate::operations::get_julia_path_from_channel;
+use crate::output::OutputFormat;
+use crate::utils::get_juliaupconfig_path;
+use anyhow::{bail, Context, Result};
+
+/// Turns the exit status of a child into the exit code juliaup should exit
+/// with. Like shells do, a child that was killed by a signal on Unix gives
+/// `128 + signal`.
+pub(crate) fn get_exit_code(status: std::process::ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+
+    status.code().unwrap_or(1)
+}
+
+/// Runs the Julia of `channel` with `args` and returns its exit code.
+pub fn run_command_run(channel: String, args: Vec<String>, install: bool) -> Result<i32> {
+    let mut config_data =
+        load_config_db().with_context(|| "`run` command failed to load configuration file.")?;
+
+    if !config_data.installed_channels.contains_key(&channel) {
+        if !install {
+            bail!("'{}' is not installed. Install it with `juliaup add {}` or pass `--install`.", channel, channel);
+        }
+
+        run_command_add(channel.clone(), false, None, None, None, OutputFormat::Text)
+            .with_context(|| format!("`run` command failed to install '{}'.", channel))?;
+
+        config_data =
+            load_config_db().with_context(|| "`run` command failed to load configuration file.")?;
+    }
+
+    let juliaupconfig_path = get_juliaupconfig_path()
+        .with_context(|| "Failed to retrieve the path of the configuration file in the `run` command.")?;
+
+    let (julia_path, julia_args) = get_julia_path_from_channel(&config_data, &channel, &juliaupconfig_path, true)
+        .with_context(|| format!("`run` command failed to determine the command for the `{}` channel.", channel))?;
+
+    let status = std::process::Command::new(&julia_path)
+        .args(&julia_args)
+        .args(&args)
+        .status()
+        .with_context(|| format!("`run` command failed to start `{}`.", julia_path.display()))?;
+
+    Ok(get_exit_code(status))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_exit_code() {
+        let status = std::process::Command::new("sh").args(["-c", "exit 3"]).status().unwrap();
+        assert_eq!(get_exit_code(status), 3);
+
+        let status = std::process::Command::new("sh").args(["-c", "kill -TERM $$"]).status().unwrap();
+        assert_eq!(get_exit_code(status), 128 + 15);
+    }
+}
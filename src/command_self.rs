@@ -0,0 +1,413 @@
+use crate::archive::{detect_archive_format, extract_archive_sans_parent};
+use crate::config_file::load_config_db;
+use crate::download_cache::download_file;
+use crate::http_client::HttpClient;
+use crate::progress::{report_info, report_status, Progress};
+use crate::command_init::{create_julia_shim, remove_from_shell_profiles};
+use crate::utils::{get_file_sha256, get_juliaup_home_path, is_interactive, prompt_confirmation, verify_file_sha256};
+use anyhow::{anyhow, bail, Context, Result};
+use semver::Version;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+// juliaup releases are published as GitHub releases by the release workflow.
+const JULIAUP_LATEST_RELEASE_URL: &str = "https://api.github.com/repos/JuliaLang/juliaup/releases/latest";
+
+/// The executables that make up a juliaup installation.
+pub const JULIAUP_BINARIES: [&str; 2] = ["juliaup", "julialauncher"];
+
+#[derive(Deserialize)]
+struct GitHubRelease {
+    tag_name: String,
+    assets: Vec<GitHubReleaseAsset>,
+}
+
+#[derive(Deserialize)]
+struct GitHubReleaseAsset {
+    name: String,
+    browser_download_url: String,
+    // GitHub records a `sha256:<hash>` digest for uploaded assets.
+    #[serde(default)]
+    digest: Option<String>,
+}
+
+pub fn get_binary_name(name: &str) -> String {
+    format!("{}{}", name, std::env::consts::EXE_SUFFIX)
+}
+
+/// Returns the folder that contains the running juliaup, which is where
+/// `julialauncher` and the `julia` shim live as well.
+pub fn get_juliaup_bin_path() -> Result<PathBuf> {
+    let juliaup_path = std::env::current_exe()
+        .with_context(|| "Failed to determine the path of the running juliaup.")?;
+
+    // The Store owns the package folder and updates it on its own.
+    if juliaup_path.components().any(|part| part.as_os_str() == "WindowsApps") {
+        bail!("This copy of juliaup was installed from the Windows Store, please use the Store to update or uninstall it.");
+    }
+
+    juliaup_path
+        .parent()
+        .map(|path| path.to_path_buf())
+        .ok_or_else(|| anyhow!("Failed to determine the folder of `{}`.", juliaup_path.display()))
+}
+
+// The release workflow names the archives after the Rust target they were
+// built for.
+fn get_release_asset_name() -> Result<&'static str> {
+    match (std::env::consts::ARCH, std::env::consts::OS) {
+        ("x86_64", "linux") => Ok("x86_64-unknown-linux-gnu.tar.gz"),
+        ("x86", "linux") => Ok("i686-unknown-linux-gnu.tar.gz"),
+        ("x86_64", "macos") => Ok("x86_64-apple-darwin.tar.gz"),
+        ("x86_64", "windows") => Ok("x86_64-pc-windows-msvc.zip"),
+        ("x86", "windows") => Ok("i686-pc-windows-msvc.zip"),
+        (arch, os) => bail!("There are no juliaup releases for {} on {}.", arch, os),
+    }
+}
+
+/// Returns the version of the release tagged `tag_name` if it is newer than
+/// `current_version`.
+fn get_update_version(current_version: &Version, tag_name: &str) -> Result<Option<Version>> {
+    let latest_version = Version::parse(tag_name.trim_start_matches('v'))
+        .with_context(|| format!("The latest juliaup release has the invalid version tag `{}`.", tag_name))?;
+
+    if latest_version > *current_version {
+        Ok(Some(latest_version))
+    } else {
+        Ok(None)
+    }
+}
+
+fn get_latest_juliaup_release(client: &HttpClient) -> Result<GitHubRelease> {
+    let response = client
+        .get(JULIAUP_LATEST_RELEASE_URL)
+        .set("Accept", "application/vnd.github+json")
+        .call()
+        .with_context(|| format!("Failed to download `{}`.", JULIAUP_LATEST_RELEASE_URL))?;
+
+    serde_json::from_reader(response.into_reader())
+        .with_context(|| format!("Failed to parse the release information from `{}`.", JULIAUP_LATEST_RELEASE_URL))
+}
+
+// Returns the folder in `extracted_path` that contains the juliaup binaries,
+// which is the archive's top level or a folder below it.
+fn find_release_binaries(extracted_path: &Path) -> Option<PathBuf> {
+    walkdir::WalkDir::new(extracted_path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .find(|entry| entry.file_type().is_file() && entry.file_name() == get_binary_name("juliaup").as_str())
+        .and_then(|entry| entry.path().parent().map(|path| path.to_path_buf()))
+}
+
+fn download_juliaup_release(
+    client: &HttpClient,
+    release: &GitHubRelease,
+    version: &Version,
+    staging_path: &Path,
+) -> Result<PathBuf> {
+    let asset_name = get_release_asset_name()?;
+
+    let asset = release
+        .assets
+        .iter()
+        .find(|asset| asset.name == asset_name)
+        .ok_or_else(|| anyhow!("The juliaup {} release has no `{}` archive.", version, asset_name))?;
+
+    let archive_path = staging_path.join(asset_name);
+
+    download_file(client, &asset.browser_download_url, &archive_path, &Progress::new_download(&format!("juliaup {}", version)))?;
+
+    // There is no signature, so this only protects against corrupt downloads,
+    // the authenticity of the release rests on the HTTPS connection to GitHub.
+    match asset.digest.as_deref().and_then(|digest| digest.strip_prefix("sha256:")) {
+        Some(sha256) => verify_file_sha256(&archive_path, sha256).with_context(|| {
+            format!("The file downloaded from url `{}` does not have the expected checksum.", asset.browser_download_url)
+        })?,
+        None => report_info(format!("  GitHub has no checksum for `{}`, skipping the checksum verification.", asset_name)),
+    }
+
+    let extracted_path = staging_path.join("juliaup");
+
+    extract_archive_sans_parent(&archive_path, detect_archive_format(asset_name, &archive_path)?, &extracted_path)
+        .with_context(|| format!("Failed to extract `{}`.", asset.browser_download_url))?;
+
+    let extracted_path = find_release_binaries(&extracted_path)
+        .ok_or_else(|| anyhow!("The juliaup {} release does not contain `{}`.", version, get_binary_name("juliaup")))?;
+
+    for name in JULIAUP_BINARIES {
+        if !extracted_path.join(get_binary_name(name)).is_file() {
+            bail!("The juliaup {} release does not contain `{}`.", version, get_binary_name(name));
+        }
+    }
+
+    let output = std::process::Command::new(extracted_path.join(get_binary_name("juliaup")))
+        .arg("--version")
+        .output()
+        .with_context(|| format!("Failed to start the downloaded juliaup {}.", version))?;
+
+    if !output.status.success() {
+        bail!("The downloaded juliaup {} does not work: {}", version, String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    Ok(extracted_path)
+}
+
+/// Moves all binaries from `source_path` into `bin_path`. The binaries they
+/// replace are moved into `backup_path` first, which also works for the
+/// running juliaup on Windows, and are moved back if anything fails, so
+/// that we never end up with a mix of versions.
+fn replace_juliaup_binaries(source_path: &Path, bin_path: &Path, backup_path: &Path) -> Result<()> {
+    std::fs::create_dir_all(backup_path)
+        .with_context(|| format!("Failed to create folder `{}`.", backup_path.display()))?;
+
+    let mut replaced: Vec<(PathBuf, Option<PathBuf>)> = Vec::new();
+
+    let mut result = Ok(());
+
+    for name in JULIAUP_BINARIES {
+        let target = bin_path.join(get_binary_name(name));
+        let backup = backup_path.join(get_binary_name(name));
+
+        let backup = if target.exists() {
+            if let Err(err) = std::fs::rename(&target, &backup) {
+                result = Err(err).with_context(|| format!("Failed to move `{}` out of the way.", target.display()));
+                break;
+            }
+            Some(backup)
+        } else {
+            None
+        };
+
+        replaced.push((target.clone(), backup));
+
+        if let Err(err) = std::fs::rename(source_path.join(get_binary_name(name)), &target) {
+            result = Err(err).with_context(|| format!("Failed to move the new `{}` into place.", target.display()));
+            break;
+        }
+    }
+
+    if result.is_err() {
+        for (target, backup) in replaced.into_iter().rev() {
+            let _ = std::fs::remove_file(&target);
+
+            if let Some(backup) = backup {
+                if let Err(err) = std::fs::rename(&backup, &target) {
+                    eprintln!("WARNING: Failed to restore `{}` from `{}`: {}", target.display(), backup.display(), err);
+                }
+            }
+        }
+    }
+
+    result
+}
+
+// Removes what is left over from earlier updates, on Windows the replaced
+// binaries can only be deleted once they are no longer running.
+fn remove_old_update_folders(bin_path: &Path) {
+    if let Ok(entries) = std::fs::read_dir(bin_path) {
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with(".juliaup-update") {
+                let _ = std::fs::remove_dir_all(entry.path());
+            }
+        }
+    }
+}
+
+pub fn run_command_self_update() -> Result<()> {
+    let bin_path = get_juliaup_bin_path()?;
+
+    let config_data =
+        load_config_db().with_context(|| "`self update` command failed to load configuration file.")?;
+
+    remove_old_update_folders(&bin_path);
+
+    let client = HttpClient::from_settings(&config_data.settings)?;
+
+    let current_version = Version::parse(env!("CARGO_PKG_VERSION"))?;
+    let release = get_latest_juliaup_release(&client)?;
+
+    let latest_version = match get_update_version(&current_version, &release.tag_name)? {
+        Some(latest_version) => latest_version,
+        None => {
+            report_info(format!("juliaup is up-to-date, version {} is the newest release.", current_version));
+            return Ok(());
+        }
+    };
+
+    report_status("Updating", format!("juliaup from {} to {}", current_version, latest_version));
+
+    // The staging folder lives next to the binaries so that they can be
+    // moved into place with a rename.
+    let staging_path = tempfile::Builder::new()
+        .prefix(".juliaup-update")
+        .tempdir_in(&bin_path)
+        .with_context(|| format!("Failed to create a temporary folder in `{}`.", bin_path.display()))?;
+
+    let extracted_path = download_juliaup_release(&client, &release, &latest_version, staging_path.path())?;
+
+    // This has to be checked before the launcher is replaced, where the shim
+    // is a copy of the launcher it is recognized by having the same content.
+    let has_julia_shim = get_julia_shim_path(&bin_path).is_some();
+
+    replace_juliaup_binaries(&extracted_path, &bin_path, &staging_path.path().join("old"))?;
+
+    if has_julia_shim {
+        if let Err(err) = create_julia_shim(&bin_path) {
+            eprintln!("WARNING: Failed to update the `julia` command in `{}`, run `juliaup init` to fix it: {:#}", bin_path.display(), err);
+        }
+    }
+
+    if let Err(err) = staging_path.close() {
+        if cfg!(not(windows)) {
+            eprintln!("WARNING: Failed to remove the temporary update folder: {}", err);
+        }
+    }
+
+    report_info(format!("juliaup was updated to version {}.", latest_version));
+
+    Ok(())
+}
+
+/// Returns the `julia` shim in `bin_path` if it is a link to our launcher.
+fn get_julia_shim_path(bin_path: &Path) -> Option<PathBuf> {
+    let shim_path = bin_path.join(get_binary_name("julia"));
+
+    let is_shim = match std::fs::read_link(&shim_path) {
+        Ok(target) => target.file_name()? == get_binary_name("julialauncher").as_str(),
+        // `juliaup init` creates a copy of the launcher where it can't
+        // create symlinks.
+        Err(_) => {
+            shim_path.is_file()
+                && get_file_sha256(&shim_path).ok()? == get_file_sha256(&bin_path.join(get_binary_name("julialauncher"))).ok()?
+        }
+    };
+
+    if is_shim {
+        Some(shim_path)
+    } else {
+        None
+    }
+}
+
+fn remove_juliaup_file(path: &Path) {
+    match std::fs::remove_file(path) {
+        Ok(()) => report_status("Removed", path.display()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => eprintln!("WARNING: Failed to remove `{}`: {}", path.display(), err),
+    }
+}
+
+pub fn run_command_self_uninstall(remove_home: bool, yes: bool) -> Result<()> {
+    let bin_path = get_juliaup_bin_path()?;
+    let home_path = get_juliaup_home_path()
+        .with_context(|| "Failed to retrieve juliaup folder while trying to uninstall juliaup.")?;
+
+    if !yes {
+        if !is_interactive() {
+            bail!("Pass `--yes` to uninstall juliaup from a non-interactive session.");
+        }
+
+        let question = if remove_home {
+            format!(
+                "This removes juliaup from `{}` and all Julia versions and settings in `{}`. Continue?",
+                bin_path.display(),
+                home_path.display()
+            )
+        } else {
+            format!("This removes juliaup from `{}`. Continue?", bin_path.display())
+        };
+
+        if !prompt_confirmation(&question)? {
+            bail!("Uninstall cancelled.");
+        }
+    }
+
+    if let Some(shim_path) = get_julia_shim_path(&bin_path) {
+        remove_juliaup_file(&shim_path);
+    }
+
+    remove_from_shell_profiles()?;
+
+    if remove_home && home_path.exists() {
+        std::fs::remove_dir_all(&home_path)
+            .with_context(|| format!("Failed to remove juliaup folder `{}`.", home_path.display()))?;
+        report_status("Removed", home_path.display());
+    }
+
+    for name in JULIAUP_BINARIES {
+        remove_juliaup_file(&bin_path.join(get_binary_name(name)));
+    }
+
+    report_info("juliaup was uninstalled.");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_update_version() {
+        let current_version = Version::parse("1.1.16-alpha.0").unwrap();
+
+        assert_eq!(get_update_version(&current_version, "v1.1.16").unwrap(), Some(Version::new(1, 1, 16)));
+        assert_eq!(get_update_version(&current_version, "v1.2.0").unwrap(), Some(Version::new(1, 2, 0)));
+        assert_eq!(get_update_version(&current_version, "v1.1.16-alpha.0").unwrap(), None);
+        assert_eq!(get_update_version(&current_version, "v1.1.15").unwrap(), None);
+        assert!(get_update_version(&current_version, "latest").is_err());
+    }
+
+    #[test]
+    fn test_julia_shim_survives_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin_path = dir.path().join("bin");
+        let source_path = dir.path().join("new");
+        std::fs::create_dir(&bin_path).unwrap();
+        std::fs::create_dir(&source_path).unwrap();
+
+        for name in JULIAUP_BINARIES {
+            std::fs::write(bin_path.join(get_binary_name(name)), "old").unwrap();
+            std::fs::write(source_path.join(get_binary_name(name)), "new").unwrap();
+        }
+
+        create_julia_shim(&bin_path).unwrap();
+        assert!(get_julia_shim_path(&bin_path).is_some());
+
+        replace_juliaup_binaries(&source_path, &bin_path, &dir.path().join("backup")).unwrap();
+        create_julia_shim(&bin_path).unwrap();
+
+        let shim_path = get_julia_shim_path(&bin_path).unwrap();
+        assert_eq!(std::fs::read_to_string(shim_path).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_replace_juliaup_binaries_rolls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin_path = dir.path().join("bin");
+        let source_path = dir.path().join("new");
+        std::fs::create_dir(&bin_path).unwrap();
+        std::fs::create_dir(&source_path).unwrap();
+
+        for name in JULIAUP_BINARIES {
+            std::fs::write(bin_path.join(get_binary_name(name)), "old").unwrap();
+        }
+
+        // The new `julialauncher` is missing, so the new `juliaup` must not stay.
+        std::fs::write(source_path.join(get_binary_name("juliaup")), "new").unwrap();
+        assert!(replace_juliaup_binaries(&source_path, &bin_path, &dir.path().join("backup")).is_err());
+
+        for name in JULIAUP_BINARIES {
+            assert_eq!(std::fs::read_to_string(bin_path.join(get_binary_name(name))).unwrap(), "old");
+        }
+
+        for name in JULIAUP_BINARIES {
+            std::fs::write(source_path.join(get_binary_name(name)), "new").unwrap();
+        }
+        replace_juliaup_binaries(&source_path, &bin_path, &dir.path().join("backup2")).unwrap();
+
+        for name in JULIAUP_BINARIES {
+            assert_eq!(std::fs::read_to_string(bin_path.join(get_binary_name(name))).unwrap(), "new");
+        }
+    }
+}
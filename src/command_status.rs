@@ -1,70 +1,188 @@
 use crate::config_file::load_config_db;
 use crate::config_file::JuliaupConfigChannel;
+use crate::output::{print_json, OutputFormat};
+use crate::utils::get_juliaup_home_path;
 use crate::versions_file::load_versions_db;
 use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
 
-pub fn run_command_status() -> Result<()> {
-    let config_data =
-        load_config_db().with_context(|| "`status` command failed to load configuration file.")?;
+#[derive(Serialize)]
+#[serde(tag = "Type")]
+enum StatusChannelKind {
+    System {
+        #[serde(rename = "Version")]
+        version: String,
+        #[serde(rename = "Path")]
+        path: Option<PathBuf>,
+        #[serde(rename = "UpdateAvailable")]
+        update_available: Option<String>,
+    },
+    DirectDownload {
+        #[serde(rename = "Version")]
+        version: String,
+        #[serde(rename = "Path")]
+        path: Option<PathBuf>,
+        #[serde(rename = "Url")]
+        url: String,
+    },
+    Linked {
+        #[serde(rename = "Command")]
+        command: String,
+        #[serde(rename = "Args")]
+        args: Vec<String>,
+    },
+}
 
-    let versiondb_data =
-        load_versions_db().with_context(|| "`status` command failed to load versions db.")?;
+#[derive(Serialize)]
+struct StatusChannel {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "IsDefault")]
+    is_default: bool,
+    #[serde(flatten)]
+    kind: StatusChannelKind,
+}
+
+#[derive(Serialize)]
+struct StatusVersion {
+    #[serde(rename = "Version")]
+    version: String,
+    #[serde(rename = "Path")]
+    path: PathBuf,
+}
+
+#[derive(Serialize)]
+struct Status {
+    #[serde(rename = "Default")]
+    default: Option<String>,
+    #[serde(rename = "Channels")]
+    channels: Vec<StatusChannel>,
+    #[serde(rename = "InstalledVersions")]
+    installed_versions: Vec<StatusVersion>,
+}
+
+fn quote_if_needed(value: &str) -> String {
+    if value.contains(' ') {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
 
+fn print_status_text(status: &Status) {
     println!("Installed Julia channels (default marked with *):");
 
-    for (key, value) in config_data.installed_channels {
-        match config_data.default {
-            Some(ref default_value) => {
-                if &key == default_value {
-                    print!("  * ");
-                } else {
-                    print!("    ")
+    for channel in &status.channels {
+        if channel.is_default {
+            print!("  * ");
+        } else {
+            print!("    ");
+        }
+        print!(" {}", channel.name);
+
+        match &channel.kind {
+            StatusChannelKind::System { version, path: _, update_available } => {
+                if let Some(update_available) = update_available {
+                    print!(" (Update from {} to {} available)", version, update_available);
                 }
             }
-            None => print!("    ")
+            StatusChannelKind::DirectDownload { version: _, path: _, url } => {
+                print!(" (installed from `{}`)", url)
+            }
+            StatusChannelKind::Linked { command, args } => {
+                let combined_command = std::iter::once(command)
+                    .chain(args.iter())
+                    .map(|part| quote_if_needed(part))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                print!(" (linked to `{}`)", combined_command)
+            }
         }
-        print!(" {}", key);
 
-        match value {
+        println!();
+    }
+}
+
+pub fn run_command_status(format: OutputFormat) -> Result<()> {
+    let config_data =
+        load_config_db().with_context(|| "`status` command failed to load configuration file.")?;
+
+    let versiondb_data =
+        load_versions_db().with_context(|| "`status` command failed to load versions db.")?;
+
+    let home_path = get_juliaup_home_path()
+        .with_context(|| "Failed to retrieve juliap folder while trying to show the status.")?;
+
+    let get_version_path = |version: &str| {
+        config_data
+            .installed_versions
+            .get(version)
+            .map(|detail| home_path.join(&detail.path).components().collect::<PathBuf>())
+    };
+
+    let mut channels = Vec::new();
+
+    for (key, value) in &config_data.installed_channels {
+        let kind = match value {
             JuliaupConfigChannel::SystemChannel { version } => {
-                match versiondb_data.available_channels.get(&key) {
-                    Some(channel) => {
-                        if channel.version != version {
-                            print!(" (Update from {} to {} available)", version, channel.version);
-                        }
-                    },
+                let update_available = match versiondb_data.available_channels.get(key) {
+                    Some(channel) if &channel.version != version => Some(channel.version.clone()),
+                    Some(_) => None,
                     None => bail!("The channel '{}' is configured as a system channel, but no such channel exists in the versions database.", key)
+                };
+
+                StatusChannelKind::System {
+                    version: version.clone(),
+                    path: get_version_path(version),
+                    update_available,
                 }
             }
-            JuliaupConfigChannel::LinkedChannel { command, args } => {
-                let mut combined_command = String::new();
-
-                if command.contains(" ") {
-                    combined_command.push_str("\"");
-                    combined_command.push_str(&command);
-                    combined_command.push_str("\"");
-                } else {
-                    combined_command.push_str(&command);
+            JuliaupConfigChannel::DirectDownloadChannel { version, url, sha256: _ } => {
+                StatusChannelKind::DirectDownload {
+                    version: version.clone(),
+                    path: get_version_path(version),
+                    url: url.clone(),
                 }
+            }
+            JuliaupConfigChannel::LinkedChannel { command, args } => StatusChannelKind::Linked {
+                command: command.clone(),
+                args: args.clone().unwrap_or_default(),
+            },
+        };
 
-                if let Some(args) = args {
-                    for i in args {
-                        combined_command.push_str(" ");
-                        if i.contains(" ") {
-                            combined_command.push_str("\"");
-                            combined_command.push_str(&i);
-                            combined_command.push_str("\"");
-                        } else {
-                            combined_command.push_str(&i);
-                        }
-                    }
-                }
+        channels.push(StatusChannel {
+            name: key.clone(),
+            is_default: config_data.default.as_ref() == Some(key),
+            kind,
+        });
+    }
 
-                print!(" (linked to `{}`)", combined_command)
-            }
-        }
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
 
-        println!();
+    let mut installed_versions: Vec<StatusVersion> = config_data
+        .installed_versions
+        .keys()
+        .filter_map(|version| {
+            get_version_path(version).map(|path| StatusVersion {
+                version: version.clone(),
+                path,
+            })
+        })
+        .collect();
+
+    installed_versions.sort_by(|a, b| a.version.cmp(&b.version));
+
+    let status = Status {
+        default: config_data.default.clone(),
+        channels,
+        installed_versions,
+    };
+
+    match format {
+        OutputFormat::Text => print_status_text(&status),
+        OutputFormat::Json => print_json(&status)?,
     }
 
     Ok(())
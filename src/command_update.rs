@@ -1,65 +1,384 @@
 use crate::config_file::JuliaupConfigChannel;
-use crate::operations::install_version;
+use crate::operations::install_versions_parallel;
 use crate::jsonstructs_versionsdb::JuliaupVersionDB;
 use crate::config_file::JuliaupConfig;
-use crate::operations::garbage_collect_versions;
+use crate::operations::{garbage_collect_versions, get_download_size, get_folder_size, get_unused_versions};
+use crate::utils::{get_juliaup_home_path, is_interactive, prompt_confirmation};
 use crate::config_file::{load_config_db, save_config_db};
 use crate::versions_file::load_versions_db;
+use crate::http_client::HttpClient;
+use crate::output::{print_json, OutputFormat};
+use crate::progress::report_info;
 use anyhow::{Context, Result,anyhow,bail};
+use console::style;
+use indicatif::HumanBytes;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
 
-fn update_channel(config_db: &mut JuliaupConfig, channel: &String, version_db: &JuliaupVersionDB) -> Result<()> {    
-    let current_version = 
-        config_db.installed_channels.get(channel).ok_or(anyhow!("asdf"))?;
+const MAX_PARALLEL_DOWNLOADS: usize = 4;
+
+enum ChannelUpdateOutcome {
+    UpToDate,
+    Updated { from: String, to: String },
+    Failed(String),
+}
+
+// Returns the version the channel should be updated to, or `None` if it is already up-to-date.
+fn get_channel_update(config_db: &JuliaupConfig, channel: &str, version_db: &JuliaupVersionDB) -> Result<Option<(String, String)>> {
+    let current_version =
+        config_db.installed_channels.get(channel).ok_or_else(|| anyhow!("'{}' is currently not installed.", channel))?;
 
     match current_version {
         JuliaupConfigChannel::SystemChannel {version} => {
-            let should_version = version_db.available_channels.get(channel).ok_or(anyhow!("asdf"))?;
+            let should_version = version_db.available_channels.get(channel)
+                .ok_or_else(|| anyhow!("The channel '{}' is configured as a system channel, but no such channel exists in the versions database.", channel))?;
 
             if &should_version.version != version {
-                install_version(&should_version.version, config_db, version_db)
-                    .with_context(|| format!("Failed to install '{}' while updating channel '{}'.", should_version.version, channel))?;
-        
-                config_db.installed_channels.insert(
-                    channel.clone(),
-                    JuliaupConfigChannel::SystemChannel {
-                        version: should_version.version.clone(),
-                    },
-                );
-            }  
+                Ok(Some((version.clone(), should_version.version.clone())))
+            } else {
+                Ok(None)
+            }
         },
+        // Channels installed from a url always point to the same archive.
+        JuliaupConfigChannel::DirectDownloadChannel {version: _, url: _, sha256: _} => Ok(None),
         JuliaupConfigChannel::LinkedChannel {command: _, args: _} => bail!("Failed to update '{}' because it is a linked channel.", channel)
     }
+}
 
-    Ok(())
+#[derive(Serialize)]
+struct UpdateResultChannel {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Result")]
+    result: &'static str,
+    #[serde(rename = "From")]
+    from: Option<String>,
+    #[serde(rename = "To")]
+    to: Option<String>,
+    #[serde(rename = "Error")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct UpdateResult {
+    #[serde(rename = "Channels")]
+    channels: Vec<UpdateResultChannel>,
+}
+
+fn print_update_json(outcomes: &[(String, ChannelUpdateOutcome)]) -> Result<()> {
+    let channels = outcomes.iter().map(|(channel, outcome)| {
+        let (result, from, to, error) = match outcome {
+            ChannelUpdateOutcome::UpToDate => ("UpToDate", None, None, None),
+            ChannelUpdateOutcome::Updated { from, to } => ("Updated", Some(from.clone()), Some(to.clone()), None),
+            ChannelUpdateOutcome::Failed(reason) => ("Failed", None, None, Some(reason.clone())),
+        };
+
+        UpdateResultChannel { name: channel.clone(), result, from, to, error }
+    }).collect();
+
+    print_json(&UpdateResult { channels })
+}
+
+#[derive(Serialize)]
+struct UpdatePlanChannel {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "From")]
+    from: String,
+    #[serde(rename = "To")]
+    to: String,
+}
+
+#[derive(Serialize)]
+struct UpdatePlanVersion {
+    #[serde(rename = "Version")]
+    version: String,
+    #[serde(rename = "Size")]
+    size: Option<u64>,
+}
+
+/// What `update` is going to do. The sizes of `downloads` are what still
+/// needs to be downloaded, the sizes of `removed_versions` the disk space
+/// that is freed.
+#[derive(Serialize)]
+struct UpdatePlan {
+    #[serde(rename = "Channels")]
+    channels: Vec<UpdatePlanChannel>,
+    #[serde(rename = "Downloads")]
+    downloads: Vec<UpdatePlanVersion>,
+    #[serde(rename = "RemovedVersions")]
+    removed_versions: Vec<UpdatePlanVersion>,
+}
+
+fn get_update_plan(
+    config_db: &JuliaupConfig,
+    version_db: &JuliaupVersionDB,
+    client: &HttpClient,
+    pending_updates: &[(String, String, String)],
+    versions_to_install: &[String],
+) -> Result<UpdatePlan> {
+    let home_path = get_juliaup_home_path()
+        .with_context(|| "Failed to retrieve juliap folder while planning the update.")?;
+
+    // The configuration after the update tells us which versions the
+    // garbage collection at the end will remove.
+    let mut updated_config_db = config_db.clone();
+    for (channel, _, to) in pending_updates {
+        updated_config_db.installed_channels.insert(
+            channel.clone(),
+            JuliaupConfigChannel::SystemChannel { version: to.clone() },
+        );
+    }
+
+    let mut removed_versions: Vec<UpdatePlanVersion> = get_unused_versions(&updated_config_db)
+        .into_iter()
+        .map(|version| {
+            let size = get_folder_size(&home_path.join(&config_db.installed_versions[&version].path));
+            UpdatePlanVersion { version, size: Some(size) }
+        })
+        .collect();
+    removed_versions.sort_by(|a, b| a.version.cmp(&b.version));
+
+    let downloads = versions_to_install
+        .iter()
+        .map(|version| {
+            Ok(UpdatePlanVersion {
+                version: version.clone(),
+                size: get_download_size(version, version_db, client)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(UpdatePlan {
+        channels: pending_updates
+            .iter()
+            .map(|(name, from, to)| UpdatePlanChannel { name: name.clone(), from: from.clone(), to: to.clone() })
+            .collect(),
+        downloads,
+        removed_versions,
+    })
+}
+
+fn format_update_plan(plan: &UpdatePlan) -> Vec<String> {
+    if plan.channels.is_empty() && plan.removed_versions.is_empty() {
+        return vec!["All channels are up-to-date.".to_string()];
+    }
+
+    let mut lines = Vec::new();
+
+    if !plan.channels.is_empty() {
+        let name_width = plan.channels.iter().map(|channel| channel.name.len()).max().unwrap_or(0);
+
+        lines.push("Channels to update:".to_string());
+        for channel in &plan.channels {
+            lines.push(format!("  {:width$}  {} -> {}", channel.name, channel.from, channel.to, width = name_width));
+        }
+    }
+
+    let mut add_versions = |title: &str, versions: &[UpdatePlanVersion], zero_size: &str| {
+        if versions.is_empty() {
+            return;
+        }
+
+        let version_width = versions.iter().map(|version| version.version.len()).max().unwrap_or(0);
+
+        lines.push(title.to_string());
+        for version in versions {
+            let size = match version.size {
+                Some(0) => zero_size.to_string(),
+                Some(size) => HumanBytes(size).to_string(),
+                None => "unknown size".to_string(),
+            };
+            lines.push(format!("  {:width$}  {}", version.version, size, width = version_width));
+        }
+
+        let total: u64 = versions.iter().filter_map(|version| version.size).sum();
+        if versions.iter().all(|version| version.size.is_some()) {
+            lines.push(format!("  Total: {}", HumanBytes(total)));
+        } else if total > 0 {
+            lines.push(format!("  Total: at least {}", HumanBytes(total)));
+        }
+    };
+
+    add_versions("Julia versions to download:", &plan.downloads, "already downloaded");
+    add_versions("Julia versions to remove:", &plan.removed_versions, "empty");
+
+    lines
+}
+
+fn print_update_summary(outcomes: &[(String, ChannelUpdateOutcome)]) {
+    let channel_width = outcomes.iter().map(|(channel, _)| channel.len()).max().unwrap_or(0).max("Channel".len());
+
+    report_info("");
+    report_info(format!("  {:width$}  Result", "Channel", width = channel_width));
+
+    for (channel, outcome) in outcomes {
+        let result = match outcome {
+            ChannelUpdateOutcome::UpToDate => "Already up-to-date".to_string(),
+            ChannelUpdateOutcome::Updated { from, to } => format!("{} from {} to {}", style("Updated").green().bold().for_stderr(), from, to),
+            ChannelUpdateOutcome::Failed(reason) => format!("{}: {}", style("Failed").red().bold().for_stderr(), reason),
+        };
+
+        report_info(format!("  {:width$}  {}", channel, result, width = channel_width));
+    }
 }
 
-pub fn run_command_update(channel: Option<String>) -> Result<()> {
+pub fn run_command_update(channel: Option<String>, skip_smoke_test: bool, dry_run: bool, yes: bool, format: OutputFormat) -> Result<()> {
     let version_db =
         load_versions_db().with_context(|| "`update` command failed to load versions db.")?;
 
     let mut config_data = load_config_db()
         .with_context(|| "`update` command failed to load configuration file.")?;
 
-    match channel {
+    let channels: Vec<String> = match channel {
         None => {
-            for (k,_) in config_data.installed_channels.clone() {
-                update_channel(&mut config_data, &k, &version_db)?;
-            }
-
+            let mut channels: Vec<String> = config_data.installed_channels.iter()
+                .filter(|(_, value)| matches!(value, JuliaupConfigChannel::SystemChannel {version: _}))
+                .map(|(key, _)| key.clone())
+                .collect();
+            channels.sort();
+            channels
         },
         Some(channel) => {
             if !config_data.installed_channels.contains_key(&channel) {
                 bail!("'{}' cannot be updated because it is currently not installed.", channel);
             }
 
-            update_channel(&mut config_data, &channel, &version_db)?;
+            get_channel_update(&config_data, &channel, &version_db)?;
+
+            vec![channel]
         }
     };
 
+    let mut outcomes: Vec<(String, ChannelUpdateOutcome)> = Vec::new();
+    let mut pending_updates: Vec<(String, String, String)> = Vec::new();
+
+    for channel in channels {
+        match get_channel_update(&config_data, &channel, &version_db) {
+            Ok(Some((from, to))) => pending_updates.push((channel, from, to)),
+            Ok(None) => outcomes.push((channel, ChannelUpdateOutcome::UpToDate)),
+            Err(err) => outcomes.push((channel, ChannelUpdateOutcome::Failed(format!("{:#}", err)))),
+        }
+    }
+
+    let versions_to_install: Vec<String> = pending_updates.iter()
+        .map(|(_, _, to)| to.clone())
+        .filter(|version| !config_data.installed_versions.contains_key(version))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let client = HttpClient::from_settings(&config_data.settings)?;
+
+    if dry_run {
+        let plan = get_update_plan(&config_data, &version_db, &client, &pending_updates, &versions_to_install)?;
+
+        match format {
+            OutputFormat::Text => {
+                for line in format_update_plan(&plan) {
+                    println!("{}", line);
+                }
+            }
+            OutputFormat::Json => print_json(&plan)?,
+        }
+
+        return Ok(());
+    }
+
+    if !yes && !pending_updates.is_empty() && is_interactive() {
+        let plan = get_update_plan(&config_data, &version_db, &client, &pending_updates, &versions_to_install)?;
+
+        for line in format_update_plan(&plan) {
+            report_info(line);
+        }
+
+        if !prompt_confirmation("Continue?")? {
+            bail!("Update cancelled.");
+        }
+    }
+
+    let mut install_errors: HashMap<String, String> = HashMap::new();
+
+    for (version, result) in install_versions_parallel(&versions_to_install, &version_db, &client, MAX_PARALLEL_DOWNLOADS, !skip_smoke_test) {
+        match result {
+            Ok(installed_version) => {
+                config_data.installed_versions.insert(version, installed_version);
+            },
+            Err(err) => {
+                install_errors.insert(version, format!("{:#}", err));
+            }
+        }
+    }
+
+    for (channel, from, to) in pending_updates {
+        let outcome = match install_errors.get(&to) {
+            Some(err) => ChannelUpdateOutcome::Failed(err.clone()),
+            None => {
+                config_data.installed_channels.insert(
+                    channel.clone(),
+                    JuliaupConfigChannel::SystemChannel {
+                        version: to.clone(),
+                    },
+                );
+                ChannelUpdateOutcome::Updated { from, to }
+            }
+        };
+        outcomes.push((channel, outcome));
+    }
+
+    outcomes.sort_by(|a, b| a.0.cmp(&b.0));
+
     garbage_collect_versions(&mut config_data)?;
 
     save_config_db(&config_data)
         .with_context(|| "`update` command failed to save configuration db.")?;
 
+    match format {
+        OutputFormat::Text => print_update_summary(&outcomes),
+        OutputFormat::Json => print_update_json(&outcomes)?,
+    }
+
+    let failed_count = outcomes.iter().filter(|(_, outcome)| matches!(outcome, ChannelUpdateOutcome::Failed(_))).count();
+
+    if failed_count > 0 {
+        bail!("Failed to update {} of {} channels.", failed_count, outcomes.len());
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_update_plan() {
+        let plan = UpdatePlan {
+            channels: vec![UpdatePlanChannel {
+                name: "release".to_string(),
+                from: "1.6.1+0~x64".to_string(),
+                to: "1.6.2+0~x64".to_string(),
+            }],
+            downloads: vec![
+                UpdatePlanVersion { version: "1.6.2+0~x64".to_string(), size: Some(2048) },
+                UpdatePlanVersion { version: "1.7.0+0~x64".to_string(), size: None },
+            ],
+            removed_versions: Vec::new(),
+        };
+
+        assert_eq!(
+            format_update_plan(&plan),
+            vec![
+                "Channels to update:",
+                "  release  1.6.1+0~x64 -> 1.6.2+0~x64",
+                "Julia versions to download:",
+                "  1.6.2+0~x64  2.00KiB",
+                "  1.7.0+0~x64  unknown size",
+                "  Total: at least 2.00KiB",
+            ]
+        );
+
+        let plan = UpdatePlan { channels: Vec::new(), downloads: Vec::new(), removed_versions: Vec::new() };
+        assert_eq!(format_update_plan(&plan), vec!["All channels are up-to-date."]);
+    }
+}
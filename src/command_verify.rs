@@ -0,0 +1,163 @@
+use crate::config_file::{load_config_db, save_config_db, JuliaupConfigChannel};
+use crate::http_client::HttpClient;
+use crate::progress::{report_info, report_status, Progress};
+use crate::install_manifest::{load_install_manifest, verify_install_manifest};
+use crate::operations::{check_disk_space, install_url_version_files, install_version_files};
+use crate::utils::{get_juliaup_home_path, is_interactive, prompt_confirmation};
+use crate::versions_file::load_versions_db;
+use anyhow::{bail, Context, Result};
+use console::style;
+use std::collections::BTreeMap;
+
+const MAX_LISTED_FILES: usize = 10;
+
+fn print_files(label: &str, files: &[String]) {
+    for file in files.iter().take(MAX_LISTED_FILES) {
+        println!("  {} {}", label, file);
+    }
+
+    if files.len() > MAX_LISTED_FILES {
+        println!("  ... and {} more {} files", files.len() - MAX_LISTED_FILES, label.trim_end_matches(':').to_lowercase());
+    }
+}
+
+pub fn run_command_verify(channel: Option<String>, reinstall: bool) -> Result<()> {
+    let mut config_data =
+        load_config_db().with_context(|| "`verify` command failed to load configuration file.")?;
+
+    let home_path = get_juliaup_home_path()
+        .with_context(|| "Failed to retrieve juliap folder while trying to verify installed versions.")?;
+
+    // Maps each installed version to the channels that use it.
+    let mut versions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for (channel_name, value) in &config_data.installed_channels {
+        match value {
+            JuliaupConfigChannel::SystemChannel { version }
+            | JuliaupConfigChannel::DirectDownloadChannel { version, url: _, sha256: _ } => {
+                versions.entry(version.clone()).or_default().push(channel_name.clone());
+            }
+            JuliaupConfigChannel::LinkedChannel { command: _, args: _ } => {}
+        }
+    }
+
+    if let Some(channel) = channel {
+        match config_data.installed_channels.get(&channel) {
+            Some(JuliaupConfigChannel::SystemChannel { version })
+            | Some(JuliaupConfigChannel::DirectDownloadChannel { version, url: _, sha256: _ }) => {
+                versions.retain(|key, _| key == version)
+            }
+            Some(JuliaupConfigChannel::LinkedChannel { command: _, args: _ }) => {
+                bail!("'{}' cannot be verified because it is a linked channel.", channel)
+            }
+            None => bail!("'{}' cannot be verified because it is currently not installed.", channel),
+        }
+    }
+
+    for channels in versions.values_mut() {
+        channels.sort();
+    }
+
+    let mut damaged_versions = Vec::new();
+
+    for (version, channels) in &versions {
+        let detail = match config_data.installed_versions.get(version) {
+            Some(detail) => detail,
+            None => continue, // Shouldn't happen, `juliaup status` reports these
+        };
+
+        println!("Julia {} ({}):", version, channels.join(", "));
+
+        let manifest = match load_install_manifest(version)? {
+            Some(manifest) => manifest,
+            None => {
+                println!("  No manifest was recorded when this version was installed, skipping.");
+                continue;
+            }
+        };
+
+        let report = verify_install_manifest(&home_path.join(&detail.path), &manifest)
+            .with_context(|| format!("Failed to verify Julia {}.", version))?;
+
+        if report.is_ok() {
+            println!("  {} All {} files are intact.", style("OK").green().bold(), manifest.files.len());
+        } else {
+            print_files("Missing:", &report.missing);
+            print_files("Modified:", &report.modified);
+            print_files("Extra:", &report.extra);
+            damaged_versions.push(version.clone());
+        }
+    }
+
+    if damaged_versions.is_empty() {
+        return Ok(());
+    }
+
+    report_info("");
+
+    let should_reinstall = reinstall
+        || (is_interactive()
+            && prompt_confirmation(&format!("Reinstall {}?", damaged_versions.join(", ")))?);
+
+    if !should_reinstall {
+        bail!(
+            "{} installed Julia versions failed verification. Run `juliaup verify --reinstall` to reinstall them.",
+            damaged_versions.len()
+        );
+    }
+
+    // Versions installed from a local file can't be downloaded again.
+    let (from_file_versions, damaged_versions): (Vec<String>, Vec<String>) = damaged_versions
+        .into_iter()
+        .partition(|version| config_data.installed_versions[version].from_file.is_some());
+
+    for version in &from_file_versions {
+        report_info(format!(
+            "Julia {} was installed from `{}` and cannot be reinstalled automatically. Remove the channels that use it, run `juliaup gc` and add it again with `--from-file`.",
+            version,
+            config_data.installed_versions[version].from_file.as_deref().unwrap_or_default()
+        ));
+    }
+
+    if !damaged_versions.is_empty() {
+        let version_db =
+            load_versions_db().with_context(|| "`verify` command failed to load versions db.")?;
+
+        let client = HttpClient::from_settings(&config_data.settings)?;
+
+        check_disk_space(&damaged_versions, &version_db, &client)?;
+
+        for version in damaged_versions {
+            report_status("Reinstalling", format!("Julia {}.", version));
+
+            let pb = Progress::new_download("  Downloading:");
+
+            let url_channel = config_data.installed_channels.values().find_map(|value| match value {
+                JuliaupConfigChannel::DirectDownloadChannel { version: channel_version, url, sha256 } if channel_version == &version => {
+                    Some((url.clone(), sha256.clone()))
+                }
+                _ => None,
+            });
+
+            let installed_version = match url_channel {
+                Some((url, sha256)) => install_url_version_files(&version, &url, sha256.as_deref(), &client, &pb, true)?,
+                None => install_version_files(&version, &version_db, &client, &pb, true)?,
+            };
+
+            config_data.installed_versions.insert(version, installed_version);
+
+            save_config_db(&config_data)
+                .with_context(|| "`verify` command failed to save configuration db.")?;
+        }
+    }
+
+    if !from_file_versions.is_empty() {
+        bail!(
+            "{} installed Julia versions failed verification and cannot be reinstalled automatically: {}.",
+            from_file_versions.len(),
+            from_file_versions.join(", ")
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use crate::config_file::load_config_db;
+use crate::operations::get_julia_path_from_channel;
+use crate::output::{print_json, OutputFormat};
+use crate::utils::get_juliaupconfig_path;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Serialize)]
+struct Which {
+    #[serde(rename = "Channel")]
+    channel: String,
+    #[serde(rename = "Path")]
+    path: PathBuf,
+    #[serde(rename = "Args")]
+    args: Vec<String>,
+}
+
+pub fn run_command_which(channel: Option<String>, format: OutputFormat) -> Result<()> {
+    let config_data =
+        load_config_db().with_context(|| "`which` command failed to load configuration file.")?;
+
+    let juliaupconfig_path = get_juliaupconfig_path()
+        .with_context(|| "Failed to retrieve the path of the configuration file in the `which` command.")?;
+
+    let julia_version_from_cmd_line = channel.is_some();
+
+    let channel = match channel {
+        Some(channel) => {
+            if !config_data.installed_channels.contains_key(&channel) {
+                bail!("'{}' is not installed.", channel);
+            }
+            channel
+        }
+        None => config_data
+            .default
+            .clone()
+            .ok_or_else(|| anyhow!("No default channel is configured, pass the channel you are interested in."))?,
+    };
+
+    let (path, args) = get_julia_path_from_channel(
+        &config_data,
+        &channel,
+        &juliaupconfig_path,
+        julia_version_from_cmd_line,
+    )?;
+
+    match format {
+        // The binary comes on a line of its own so that scripts can use the
+        // output directly, linked channels list their arguments one per line
+        // after it.
+        OutputFormat::Text => {
+            println!("{}", path.display());
+
+            for arg in &args {
+                println!("{}", arg);
+            }
+        }
+        OutputFormat::Json => print_json(&Which { channel, path, args })?,
+    }
+
+    Ok(())
+}
@@ -9,11 +9,24 @@ use std::io::{BufReader, ErrorKind};
 pub struct JuliaupConfigVersion {
     #[serde(rename = "Path")]
     pub path: String,
+    // Versions installed with `add --from-file` can't be downloaded again.
+    #[serde(rename = "FromFile", default, skip_serializing_if = "Option::is_none")]
+    pub from_file: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 pub enum JuliaupConfigChannel {
+    // This needs to come before `SystemChannel`, which would otherwise match
+    // because it ignores the additional fields.
+    DirectDownloadChannel {
+        #[serde(rename = "Version")]
+        version: String,
+        #[serde(rename = "Url")]
+        url: String,
+        #[serde(rename = "Sha256", default, skip_serializing_if = "Option::is_none")]
+        sha256: Option<String>,
+    },
     SystemChannel {
         #[serde(rename = "Version")]
         version: String,
@@ -26,6 +39,22 @@ pub enum JuliaupConfigChannel {
     },
 }
 
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct JuliaupConfigSettings {
+    #[serde(rename = "HttpConnectTimeout", default, skip_serializing_if = "Option::is_none")]
+    pub http_connect_timeout: Option<u64>,
+    #[serde(rename = "HttpReadTimeout", default, skip_serializing_if = "Option::is_none")]
+    pub http_read_timeout: Option<u64>,
+    #[serde(rename = "HttpRetries", default, skip_serializing_if = "Option::is_none")]
+    pub http_retries: Option<u32>,
+    #[serde(rename = "HttpProxy", default, skip_serializing_if = "Option::is_none")]
+    pub http_proxy: Option<String>,
+    #[serde(rename = "HttpNoProxy", default, skip_serializing_if = "Option::is_none")]
+    pub http_no_proxy: Option<String>,
+    #[serde(rename = "CaBundle", default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct JuliaupConfig {
     #[serde(rename = "Default")]
@@ -34,6 +63,8 @@ pub struct JuliaupConfig {
     pub installed_versions: HashMap<String, JuliaupConfigVersion>,
     #[serde(rename = "InstalledChannels")]
     pub installed_channels: HashMap<String, JuliaupConfigChannel>,
+    #[serde(rename = "Settings", default)]
+    pub settings: JuliaupConfigSettings,
 }
 
 pub fn load_config_db() -> Result<JuliaupConfig> {
@@ -50,6 +81,7 @@ pub fn load_config_db() -> Result<JuliaupConfig> {
                     default: None,
                     installed_versions: HashMap::new(),
                     installed_channels: HashMap::new(),
+                    settings: JuliaupConfigSettings::default(),
                 })
             },
             other_error => {
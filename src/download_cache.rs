@@ -0,0 +1,534 @@
+use crate::http_client::HttpClient;
+use crate::progress::Progress;
+use crate::utils::{get_file_sha256, get_juliaup_cache_path};
+use anyhow::{anyhow, bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const PARTIAL_DOWNLOAD_SUFFIX: &str = ".part";
+const CHECKSUM_SUFFIX: &str = ".sha256";
+
+enum DownloadAttemptError {
+    // The attempt failed in a way that a new attempt can resume from, e.g. a dropped connection.
+    Transient(anyhow::Error),
+    Permanent(anyhow::Error),
+}
+
+pub enum CachedDownload {
+    Complete(u64),
+    Partial(u64),
+    Missing,
+}
+
+pub struct CachedFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub partial: bool,
+}
+
+// The cache is keyed by a hash of the full url, so that two urls that end in
+// the same file name never share a cached file. The file name is kept at the
+// end to make `juliaup cache list` readable.
+fn get_cache_filename(url: &str) -> Result<String> {
+    let url_path = url.split(['?', '#']).next().unwrap(); // unwrap OK because split always returns a first element
+
+    match url_path.rsplit('/').next() {
+        Some(filename) if !filename.is_empty() => {
+            let url_hash = hex::encode(Sha256::digest(url.as_bytes()));
+            Ok(format!("{}-{}", &url_hash[..16], filename))
+        }
+        _ => bail!("Failed to determine a file name for the download from url `{}`.", url),
+    }
+}
+
+fn with_suffix(target_path: &Path, suffix: &str) -> PathBuf {
+    let mut filename = target_path.file_name().unwrap_or_default().to_os_string();
+    filename.push(suffix);
+    target_path.with_file_name(filename)
+}
+
+fn get_partial_download_path(target_path: &Path) -> PathBuf {
+    with_suffix(target_path, PARTIAL_DOWNLOAD_SUFFIX)
+}
+
+fn get_checksum_path(target_path: &Path) -> PathBuf {
+    with_suffix(target_path, CHECKSUM_SUFFIX)
+}
+
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    // Format is `bytes <start>-<end>/<total>` or `bytes */<total>`, where total can be `*`.
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+
+    let start = match range {
+        "*" => 0,
+        range => range.split_once('-')?.0.parse::<u64>().ok()?,
+    };
+
+    Some((start, total.parse::<u64>().ok()))
+}
+
+fn continue_download(
+    client: &HttpClient,
+    url: &str,
+    partial_path: &Path,
+    pb: &Progress,
+) -> std::result::Result<(), DownloadAttemptError> {
+    let existing_size = std::fs::metadata(partial_path).map(|m| m.len()).unwrap_or(0);
+
+    let mut request = client.get(url);
+    if existing_size > 0 {
+        request = request.set("Range", &format!("bytes={}-", existing_size));
+    }
+
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(416, response)) => {
+            // The server tells us that our partial file already has all the data
+            // or that it is not a prefix of the resource anymore.
+            let total = response
+                .header("Content-Range")
+                .and_then(parse_content_range)
+                .and_then(|(_, total)| total);
+
+            if total == Some(existing_size) {
+                return Ok(());
+            }
+
+            let _ = std::fs::remove_file(partial_path);
+            return Err(DownloadAttemptError::Transient(anyhow!(
+                "The server rejected the request to resume the download from url `{}`.",
+                url
+            )));
+        }
+        Err(err @ ureq::Error::Status(429, _)) | Err(err @ ureq::Error::Status(500..=599, _)) => {
+            return Err(DownloadAttemptError::Transient(
+                anyhow::Error::new(err).context(format!("Failed to download from url `{}`.", url)),
+            ))
+        }
+        Err(err @ ureq::Error::Status(_, _)) => {
+            return Err(DownloadAttemptError::Permanent(
+                anyhow::Error::new(err).context(format!("Failed to download from url `{}`.", url)),
+            ))
+        }
+        Err(err) => {
+            return Err(DownloadAttemptError::Transient(
+                anyhow::Error::new(err).context(format!("Failed to download from url `{}`.", url)),
+            ))
+        }
+    };
+
+    let resume_range = if response.status() == 206 {
+        response.header("Content-Range").and_then(parse_content_range)
+    } else {
+        None
+    };
+
+    let (mut file, offset, total_size) = match resume_range {
+        Some((start, total)) if start == existing_size => {
+            let file = OpenOptions::new()
+                .append(true)
+                .open(partial_path)
+                .map_err(|err| DownloadAttemptError::Permanent(err.into()))?;
+            (file, start, total)
+        }
+        _ => {
+            // Either a fresh download or a server that ignored our range request, in
+            // both cases the response contains the entire file.
+            let total = response
+                .header("Content-Length")
+                .and_then(|v| v.parse::<u64>().ok());
+            let file = File::create(partial_path)
+                .map_err(|err| DownloadAttemptError::Permanent(err.into()))?;
+            (file, 0, total)
+        }
+    };
+
+    if let Some(total_size) = total_size {
+        pb.set_length(total_size);
+    }
+    pb.set_position(offset);
+
+    let mut reader = pb.wrap_read(response.into_reader());
+
+    std::io::copy(&mut reader, &mut file)
+        .and_then(|_| file.flush())
+        .map_err(|err| {
+            DownloadAttemptError::Transient(
+                anyhow::Error::new(err).context(format!("Failed to download from url `{}`.", url)),
+            )
+        })?;
+
+    let downloaded_size = std::fs::metadata(partial_path)
+        .map_err(|err| DownloadAttemptError::Permanent(err.into()))?
+        .len();
+
+    match total_size {
+        Some(total_size) if downloaded_size < total_size => {
+            Err(DownloadAttemptError::Transient(anyhow!(
+                "The connection was closed after {} of {} bytes were downloaded from url `{}`.",
+                downloaded_size,
+                total_size,
+                url
+            )))
+        }
+        Some(total_size) if downloaded_size > total_size => {
+            let _ = std::fs::remove_file(partial_path);
+            Err(DownloadAttemptError::Permanent(anyhow!(
+                "The file downloaded from url `{}` is {} bytes large, but the server announced {} bytes.",
+                url,
+                downloaded_size,
+                total_size
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Downloads `url` to `target_path`. The data is first written to a `.part`
+/// file next to the target, which is resumed with HTTP range requests if a
+/// previous attempt was interrupted, and only renamed to `target_path` once
+/// its size matches what the server announced.
+pub fn download_file(client: &HttpClient, url: &str, target_path: &Path, pb: &Progress) -> Result<()> {
+    let partial_path = get_partial_download_path(target_path);
+
+    let mut attempt = 1;
+    loop {
+        match continue_download(client, url, &partial_path, pb) {
+            Ok(()) => break,
+            Err(DownloadAttemptError::Transient(err)) if attempt < client.max_attempts() => {
+                let delay = client.backoff_delay(attempt);
+                pb.println(format!(
+                    "  Download interrupted, retrying in {}s: {}",
+                    delay.as_secs(),
+                    err.root_cause()
+                ));
+                std::thread::sleep(delay);
+                attempt += 1;
+            }
+            Err(DownloadAttemptError::Transient(err)) | Err(DownloadAttemptError::Permanent(err)) => {
+                pb.abandon();
+                return Err(err);
+            }
+        }
+    }
+
+    pb.finish();
+
+    std::fs::rename(&partial_path, target_path).with_context(|| {
+        format!(
+            "Failed to move downloaded file `{}` to `{}`.",
+            partial_path.display(),
+            target_path.display()
+        )
+    })?;
+
+    Ok(())
+}
+
+fn get_cache_target_path(url: &str) -> Result<PathBuf> {
+    let cache_path = get_juliaup_cache_path()
+        .with_context(|| "Failed to retrieve the juliaup cache folder.")?;
+
+    Ok(cache_path.join(get_cache_filename(url)?))
+}
+
+/// Returns how much of `url` is already in the cache.
+pub fn get_cached_download(url: &str) -> Result<CachedDownload> {
+    let target_path = get_cache_target_path(url)?;
+
+    if let Ok(metadata) = std::fs::metadata(&target_path) {
+        return Ok(CachedDownload::Complete(metadata.len()));
+    }
+
+    match std::fs::metadata(get_partial_download_path(&target_path)) {
+        Ok(metadata) => Ok(CachedDownload::Partial(metadata.len())),
+        Err(_) => Ok(CachedDownload::Missing),
+    }
+}
+
+// A complete cached file can only be reused if it still has the checksum that
+// was recorded when it was downloaded, and the one the caller expects.
+fn is_cached_file_valid(target_path: &Path, expected_sha256: Option<&str>) -> Result<bool> {
+    let recorded_sha256 = match std::fs::read_to_string(get_checksum_path(target_path)) {
+        Ok(recorded_sha256) => recorded_sha256.trim().to_string(),
+        Err(_) => return Ok(false),
+    };
+
+    if let Some(expected_sha256) = expected_sha256 {
+        if !recorded_sha256.eq_ignore_ascii_case(expected_sha256.trim()) {
+            return Ok(false);
+        }
+    }
+
+    Ok(get_file_sha256(target_path)?.eq_ignore_ascii_case(&recorded_sha256))
+}
+
+fn download_to_cache_path(
+    cache_path: &Path,
+    client: &HttpClient,
+    url: &str,
+    expected_sha256: Option<&str>,
+    pb: &Progress,
+) -> Result<PathBuf> {
+    let target_path = cache_path.join(get_cache_filename(url)?);
+
+    std::fs::create_dir_all(cache_path).with_context(|| {
+        format!("Failed to create cache folder `{}`.", cache_path.display())
+    })?;
+
+    if target_path.exists() {
+        if is_cached_file_valid(&target_path, expected_sha256)? {
+            return Ok(target_path);
+        }
+
+        remove_cached_file(&target_path)?;
+    }
+
+    download_file(client, url, &target_path, pb)?;
+
+    let actual_sha256 = get_file_sha256(&target_path)?;
+
+    if let Some(expected_sha256) = expected_sha256 {
+        if !actual_sha256.eq_ignore_ascii_case(expected_sha256.trim()) {
+            remove_cached_file(&target_path)?;
+            bail!(
+                "The file downloaded from url `{}` has the SHA-256 checksum {}, but {} was expected.",
+                url,
+                actual_sha256,
+                expected_sha256.trim()
+            );
+        }
+    }
+
+    std::fs::write(get_checksum_path(&target_path), &actual_sha256).with_context(|| {
+        format!("Failed to record the checksum of cached file `{}`.", target_path.display())
+    })?;
+
+    Ok(target_path)
+}
+
+/// Returns the path of the cached copy of `url`, downloading it first if the
+/// cache does not contain a complete copy yet. The download is checked against
+/// `expected_sha256` if given, and a cached copy is only reused if it still
+/// has the checksum it had when it was downloaded.
+pub fn download_to_cache(
+    client: &HttpClient,
+    url: &str,
+    expected_sha256: Option<&str>,
+    pb: &Progress,
+) -> Result<PathBuf> {
+    let cache_path = get_juliaup_cache_path()
+        .with_context(|| "Failed to retrieve the juliaup cache folder.")?;
+
+    download_to_cache_path(&cache_path, client, url, expected_sha256, pb)
+}
+
+pub fn list_cached_files() -> Result<Vec<CachedFile>> {
+    let cache_path = get_juliaup_cache_path()
+        .with_context(|| "Failed to retrieve the juliaup cache folder.")?;
+
+    if !cache_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+
+    for entry in std::fs::read_dir(&cache_path)
+        .with_context(|| format!("Failed to read cache folder `{}`.", cache_path.display()))?
+    {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        // Recorded checksums belong to their cached file and go with it.
+        if entry.file_name().to_string_lossy().ends_with(CHECKSUM_SUFFIX) {
+            continue;
+        }
+
+        if metadata.is_file() {
+            files.push(CachedFile {
+                partial: entry.file_name().to_string_lossy().ends_with(PARTIAL_DOWNLOAD_SUFFIX),
+                path: entry.path(),
+                size: metadata.len(),
+            });
+        }
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(files)
+}
+
+pub fn remove_cached_file(path: &Path) -> Result<()> {
+    std::fs::remove_file(path)
+        .with_context(|| format!("Failed to delete cached file `{}`.", path.display()))?;
+
+    match std::fs::remove_file(get_checksum_path(path)) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err).with_context(|| format!("Failed to delete the checksum of cached file `{}`.", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::{serve_http, HttpClientOptions};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    // Serves `payload`, but closes every connection after at most `chunk_size` bytes of the body.
+    fn serve_flaky(payload: Vec<u8>, chunk_size: usize, honor_range: bool) -> (String, Arc<AtomicUsize>) {
+        serve_http("/julia-1.2.3-test.tar.gz", move |stream, _, range_start| {
+            let start = match range_start {
+                Some(start) if honor_range => start as usize,
+                _ => 0,
+            };
+
+            let header = if start > 0 {
+                format!(
+                    "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                    payload.len() - start,
+                    start,
+                    payload.len() - 1,
+                    payload.len()
+                )
+            } else {
+                format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", payload.len())
+            };
+
+            let end = std::cmp::min(start + chunk_size, payload.len());
+            let _ = stream.write_all(header.as_bytes());
+            let _ = stream.write_all(&payload[start..end]);
+        })
+    }
+
+    // Answers the first `failures` requests with `status` and all later ones with `payload`.
+    fn serve_failing(payload: Vec<u8>, failures: usize, status: &'static str) -> (String, Arc<AtomicUsize>) {
+        serve_http("/julia-1.2.3-test.tar.gz", move |stream, request_count, _| {
+            if request_count <= failures {
+                let _ = write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+            } else {
+                let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", payload.len());
+                let _ = stream.write_all(&payload);
+            }
+        })
+    }
+
+    fn test_client() -> HttpClient {
+        HttpClient::new(HttpClientOptions {
+            initial_backoff: Duration::ZERO,
+            ..HttpClientOptions::default()
+        })
+        .unwrap()
+    }
+
+    fn test_payload() -> Vec<u8> {
+        (0..10_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_download_resumes_after_dropped_connection() {
+        let payload = test_payload();
+        let (url, requests) = serve_flaky(payload.clone(), 3_000, true);
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("julia.tar.gz");
+
+        download_file(&test_client(), &url, &target, &Progress::hidden()).unwrap();
+
+        assert_eq!(std::fs::read(&target).unwrap(), payload);
+        assert!(!get_partial_download_path(&target).exists());
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_download_resumes_existing_partial_file() {
+        let payload = test_payload();
+        let (url, requests) = serve_flaky(payload.clone(), payload.len(), true);
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("julia.tar.gz");
+        std::fs::write(get_partial_download_path(&target), &payload[..4_000]).unwrap();
+
+        download_file(&test_client(), &url, &target, &Progress::hidden()).unwrap();
+
+        assert_eq!(std::fs::read(&target).unwrap(), payload);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_download_gives_up_when_no_attempt_completes() {
+        let payload = test_payload();
+        let (url, requests) = serve_flaky(payload, 1_000, false);
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("julia.tar.gz");
+
+        assert!(download_file(&test_client(), &url, &target, &Progress::hidden()).is_err());
+        assert!(!target.exists());
+        assert_eq!(requests.load(Ordering::SeqCst), test_client().max_attempts() as usize);
+    }
+
+    #[test]
+    fn test_download_retries_server_errors() {
+        let payload = test_payload();
+        let (url, requests) = serve_failing(payload.clone(), 2, "503 Service Unavailable");
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("julia.tar.gz");
+
+        download_file(&test_client(), &url, &target, &Progress::hidden()).unwrap();
+
+        assert_eq!(std::fs::read(&target).unwrap(), payload);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_download_does_not_retry_client_errors() {
+        let (url, requests) = serve_failing(test_payload(), usize::MAX, "404 Not Found");
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("julia.tar.gz");
+
+        assert!(download_file(&test_client(), &url, &target, &Progress::hidden()).is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_get_cache_filename() {
+        let official = get_cache_filename("https://julialang-s3.julialang.org/bin/linux/x64/1.6/julia-1.6.2-linux-x86_64.tar.gz").unwrap();
+        let mirror = get_cache_filename("https://mirror.example.com/bin/linux/x64/1.6/julia-1.6.2-linux-x86_64.tar.gz").unwrap();
+        assert!(official.ends_with("-julia-1.6.2-linux-x86_64.tar.gz"));
+        assert!(mirror.ends_with("-julia-1.6.2-linux-x86_64.tar.gz"));
+        assert_ne!(official, mirror);
+
+        assert!(get_cache_filename("https://example.com/julia.tar.gz?token=1").unwrap().ends_with("-julia.tar.gz"));
+        assert!(get_cache_filename("https://example.com/").is_err());
+    }
+
+    #[test]
+    fn test_download_to_cache_checks_cached_file() {
+        let payload = test_payload();
+        let (url, requests) = serve_flaky(payload.clone(), payload.len(), true);
+        let dir = tempfile::tempdir().unwrap();
+        let sha256 = hex::encode(Sha256::digest(&payload));
+
+        let cached = download_to_cache_path(dir.path(), &test_client(), &url, Some(&sha256), &Progress::hidden()).unwrap();
+        assert_eq!(std::fs::read(&cached).unwrap(), payload);
+        assert_eq!(std::fs::read_to_string(get_checksum_path(&cached)).unwrap(), sha256);
+
+        download_to_cache_path(dir.path(), &test_client(), &url, Some(&sha256), &Progress::hidden()).unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // A cached file that changed on disk is downloaded again.
+        std::fs::write(&cached, b"corrupt").unwrap();
+        download_to_cache_path(dir.path(), &test_client(), &url, None, &Progress::hidden()).unwrap();
+        assert_eq!(std::fs::read(&cached).unwrap(), payload);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // So is one that doesn't match the checksum the caller expects.
+        let wrong_sha256 = hex::encode(Sha256::digest(b"something else"));
+        assert!(download_to_cache_path(dir.path(), &test_client(), &url, Some(&wrong_sha256), &Progress::hidden()).is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert!(!cached.exists());
+        assert!(!get_checksum_path(&cached).exists());
+    }
+}
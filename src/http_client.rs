@@ -0,0 +1,337 @@
+use crate::config_file::JuliaupConfigSettings;
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The resolved configuration for `HttpClient`. Values from environment
+/// variables take precedence over the `Settings` in the configuration file.
+pub struct HttpClientOptions {
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    pub retries: u32,
+    pub initial_backoff: Duration,
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    pub no_proxy: Vec<String>,
+    pub ca_bundle: Option<PathBuf>,
+}
+
+impl Default for HttpClientOptions {
+    fn default() -> Self {
+        HttpClientOptions {
+            connect_timeout: Duration::from_secs(30),
+            read_timeout: Duration::from_secs(30),
+            retries: 4,
+            initial_backoff: Duration::from_secs(1),
+            http_proxy: None,
+            https_proxy: None,
+            no_proxy: Vec::new(),
+            ca_bundle: None,
+        }
+    }
+}
+
+fn get_env(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+fn get_env_number<T: std::str::FromStr>(name: &str) -> Result<Option<T>> {
+    match get_env(&[name]) {
+        Some(value) => value.trim().parse::<T>().map(Some).map_err(|_| {
+            anyhow!(
+                "The `{}` environment variable contains `{}`, which is not a valid number.",
+                name,
+                value
+            )
+        }),
+        None => Ok(None),
+    }
+}
+
+fn parse_no_proxy(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|entry| entry.trim().to_ascii_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+impl HttpClientOptions {
+    pub fn from_settings(settings: &JuliaupConfigSettings) -> Result<HttpClientOptions> {
+        let defaults = HttpClientOptions::default();
+
+        let connect_timeout = get_env_number("JULIAUP_HTTP_CONNECT_TIMEOUT")?
+            .or(settings.http_connect_timeout)
+            .map_or(defaults.connect_timeout, Duration::from_secs);
+        let read_timeout = get_env_number("JULIAUP_HTTP_READ_TIMEOUT")?
+            .or(settings.http_read_timeout)
+            .map_or(defaults.read_timeout, Duration::from_secs);
+        let retries = get_env_number("JULIAUP_HTTP_RETRIES")?
+            .or(settings.http_retries)
+            .unwrap_or(defaults.retries);
+
+        let all_proxy = get_env(&["ALL_PROXY", "all_proxy"]).or_else(|| settings.http_proxy.clone());
+        let http_proxy = get_env(&["http_proxy", "HTTP_PROXY"]).or_else(|| all_proxy.clone());
+        let https_proxy = get_env(&["https_proxy", "HTTPS_PROXY"]).or(all_proxy);
+
+        let no_proxy = get_env(&["no_proxy", "NO_PROXY"])
+            .or_else(|| settings.http_no_proxy.clone())
+            .map_or_else(Vec::new, |value| parse_no_proxy(&value));
+
+        let ca_bundle = get_env(&["JULIAUP_CA_BUNDLE"])
+            .or_else(|| settings.ca_bundle.clone())
+            .map(PathBuf::from);
+
+        Ok(HttpClientOptions {
+            connect_timeout,
+            read_timeout,
+            retries,
+            initial_backoff: defaults.initial_backoff,
+            http_proxy,
+            https_proxy,
+            no_proxy,
+            ca_bundle,
+        })
+    }
+}
+
+/// The HTTP client that juliaup uses for all downloads. It holds one agent
+/// per proxy configuration and picks the right one for each url.
+pub struct HttpClient {
+    options: HttpClientOptions,
+    direct: ureq::Agent,
+    http_proxied: Option<ureq::Agent>,
+    https_proxied: Option<ureq::Agent>,
+}
+
+fn load_tls_config(ca_bundle: &Path) -> Result<Arc<rustls::ClientConfig>> {
+    let mut tls_config = rustls::ClientConfig::new();
+    tls_config
+        .root_store
+        .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+
+    let file = File::open(ca_bundle)
+        .with_context(|| format!("Failed to open CA bundle `{}`.", ca_bundle.display()))?;
+
+    let (added, _) = tls_config
+        .root_store
+        .add_pem_file(&mut BufReader::new(file))
+        .map_err(|_| anyhow!("Failed to parse CA bundle `{}`.", ca_bundle.display()))?;
+
+    if added == 0 {
+        bail!(
+            "The CA bundle `{}` does not contain any valid certificates.",
+            ca_bundle.display()
+        );
+    }
+
+    Ok(Arc::new(tls_config))
+}
+
+fn build_agent(
+    options: &HttpClientOptions,
+    tls_config: &Option<Arc<rustls::ClientConfig>>,
+    proxy: Option<&str>,
+) -> Result<ureq::Agent> {
+    let mut builder = ureq::builder()
+        .timeout_connect(options.connect_timeout)
+        .timeout_read(options.read_timeout);
+
+    if let Some(tls_config) = tls_config {
+        builder = builder.tls_config(tls_config.clone());
+    }
+
+    if let Some(proxy) = proxy {
+        let proxy = ureq::Proxy::new(proxy.trim_end_matches('/'))
+            .with_context(|| format!("`{}` is not a valid proxy url.", proxy))?;
+        builder = builder.proxy(proxy);
+    }
+
+    Ok(builder.build())
+}
+
+// Returns the lower case host part of `url`, without user info and port.
+fn get_url_host(url: &str) -> Option<String> {
+    let authority = url.split_once("://")?.1.split(['/', '?', '#']).next()?;
+    let host_port = authority.rsplit('@').next()?;
+
+    let host = if let Some(ipv6) = host_port.strip_prefix('[') {
+        ipv6.split(']').next()?
+    } else {
+        host_port.split(':').next()?
+    };
+
+    Some(host.to_ascii_lowercase())
+}
+
+fn matches_no_proxy(host: &str, no_proxy: &[String]) -> bool {
+    no_proxy.iter().any(|entry| {
+        let entry = entry.split(':').next().unwrap_or_default(); // Ports are not taken into account
+        entry == "*"
+            || host == entry.trim_start_matches('.')
+            || host.ends_with(&format!(".{}", entry.trim_start_matches('.')))
+    })
+}
+
+impl HttpClient {
+    pub fn new(options: HttpClientOptions) -> Result<HttpClient> {
+        let tls_config = match &options.ca_bundle {
+            Some(ca_bundle) => Some(load_tls_config(ca_bundle)?),
+            None => None,
+        };
+
+        let direct = build_agent(&options, &tls_config, None)?;
+        let http_proxied = match &options.http_proxy {
+            Some(proxy) => Some(build_agent(&options, &tls_config, Some(proxy))?),
+            None => None,
+        };
+        let https_proxied = match &options.https_proxy {
+            Some(proxy) => Some(build_agent(&options, &tls_config, Some(proxy))?),
+            None => None,
+        };
+
+        Ok(HttpClient {
+            options,
+            direct,
+            http_proxied,
+            https_proxied,
+        })
+    }
+
+    pub fn from_settings(settings: &JuliaupConfigSettings) -> Result<HttpClient> {
+        let options = HttpClientOptions::from_settings(settings)?;
+
+        HttpClient::new(options).with_context(|| "Failed to configure the HTTP client.")
+    }
+
+    fn get_agent(&self, url: &str) -> &ureq::Agent {
+        let proxied = if url.starts_with("https://") {
+            &self.https_proxied
+        } else {
+            &self.http_proxied
+        };
+
+        match proxied {
+            Some(agent) => match get_url_host(url) {
+                Some(host) if matches_no_proxy(&host, &self.options.no_proxy) => &self.direct,
+                _ => agent,
+            },
+            None => &self.direct,
+        }
+    }
+
+    pub fn get(&self, url: &str) -> ureq::Request {
+        self.get_agent(url).get(url)
+    }
+
+    /// Returns the size of the resource at `url` as reported by a HEAD
+    /// request, or `None` if the server doesn't tell.
+    pub fn get_content_length(&self, url: &str) -> Option<u64> {
+        self.get_agent(url)
+            .head(url)
+            .call()
+            .ok()?
+            .header("Content-Length")?
+            .parse::<u64>()
+            .ok()
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.options.retries + 1
+    }
+
+    /// The time to wait before the next attempt, after `attempt` attempts failed.
+    pub fn backoff_delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+
+        std::cmp::min(self.options.initial_backoff.saturating_mul(factor), MAX_BACKOFF)
+    }
+}
+
+/// Starts a local HTTP server for tests and returns the url of `path` on it,
+/// along with the number of requests it received so far. `respond` writes
+/// the whole response, it gets the number of the request, starting at 1, and
+/// the start of the requested range, if any.
+#[cfg(test)]
+pub(crate) fn serve_http(
+    path: &str,
+    respond: impl Fn(&mut std::net::TcpStream, usize, Option<u64>) + Send + 'static,
+) -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+    use std::io::BufRead;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}{}", listener.local_addr().unwrap(), path);
+    let requests = Arc::new(AtomicUsize::new(0));
+    let requests_in_thread = requests.clone();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let request_count = requests_in_thread.fetch_add(1, Ordering::SeqCst) + 1;
+
+            let mut reader = BufReader::new(&stream);
+            let mut range_start = None;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                    range_start = value.trim().trim_end_matches('-').parse().ok();
+                }
+            }
+
+            respond(&mut stream, request_count, range_start);
+        }
+    });
+
+    (url, requests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay() {
+        let client = HttpClient::new(HttpClientOptions::default()).unwrap();
+
+        assert_eq!(client.max_attempts(), 5);
+        assert_eq!(client.backoff_delay(1), Duration::from_secs(1));
+        assert_eq!(client.backoff_delay(2), Duration::from_secs(2));
+        assert_eq!(client.backoff_delay(4), Duration::from_secs(8));
+        assert_eq!(client.backoff_delay(10), MAX_BACKOFF);
+        assert_eq!(client.backoff_delay(100), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_get_url_host() {
+        assert_eq!(get_url_host("https://julialang-s3.julialang.org/bin/x.tar.gz").unwrap(), "julialang-s3.julialang.org");
+        assert_eq!(get_url_host("http://user:pw@Example.com:8080?x=1").unwrap(), "example.com");
+        assert_eq!(get_url_host("http://[::1]:8080/x").unwrap(), "::1");
+        assert!(get_url_host("not a url").is_none());
+    }
+
+    #[test]
+    fn test_matches_no_proxy() {
+        let no_proxy = parse_no_proxy("localhost, .internal.example.com,mirror.org:8080");
+
+        assert!(matches_no_proxy("localhost", &no_proxy));
+        assert!(matches_no_proxy("internal.example.com", &no_proxy));
+        assert!(matches_no_proxy("pkg.internal.example.com", &no_proxy));
+        assert!(matches_no_proxy("mirror.org", &no_proxy));
+        assert!(matches_no_proxy("a.mirror.org", &no_proxy));
+        assert!(!matches_no_proxy("julialang-s3.julialang.org", &no_proxy));
+        assert!(!matches_no_proxy("notmirror.org", &no_proxy));
+        assert!(matches_no_proxy("anything", &parse_no_proxy("*")));
+    }
+}
@@ -0,0 +1,213 @@
+use crate::utils::{get_file_sha256, get_juliaup_manifests_path};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct InstallManifestFile {
+    #[serde(rename = "Size")]
+    pub size: u64,
+    #[serde(rename = "Sha256")]
+    pub sha256: String,
+    #[serde(rename = "LinkTarget", default, skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<String>,
+}
+
+/// The files of an installed Julia version as they were right after
+/// extraction, keyed by their path relative to the version folder.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InstallManifest {
+    #[serde(rename = "Files")]
+    pub files: BTreeMap<String, InstallManifestFile>,
+}
+
+#[derive(Default)]
+pub struct VerifyReport {
+    pub missing: Vec<String>,
+    pub modified: Vec<String>,
+    pub extra: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.modified.is_empty() && self.extra.is_empty()
+    }
+}
+
+fn get_manifest_path(fullversion: &str) -> Result<PathBuf> {
+    Ok(get_juliaup_manifests_path()?.join(format!("julia-{}.json", fullversion)))
+}
+
+// Manifest keys always use `/` so that they don't depend on the platform.
+fn get_manifest_key(relative_path: &Path) -> String {
+    relative_path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Computes the manifest for all files and symlinks in `folder`. Directories
+/// are not recorded, they are implied by the files in them.
+pub fn compute_install_manifest(folder: &Path) -> Result<InstallManifest> {
+    let mut files = BTreeMap::new();
+
+    for entry in walkdir::WalkDir::new(folder).follow_links(false) {
+        let entry = entry.with_context(|| format!("Failed to list the files in `{}`.", folder.display()))?;
+
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            continue;
+        }
+
+        let relative_path = entry.path().strip_prefix(folder).unwrap(); // walkdir only returns paths inside `folder`
+
+        let manifest_file = if file_type.is_symlink() {
+            let link_target = std::fs::read_link(entry.path())
+                .with_context(|| format!("Failed to read symlink `{}`.", entry.path().display()))?;
+
+            InstallManifestFile {
+                size: 0,
+                sha256: String::new(),
+                link_target: Some(link_target.to_string_lossy().into_owned()),
+            }
+        } else {
+            let metadata = entry
+                .metadata()
+                .with_context(|| format!("Failed to read the metadata of `{}`.", entry.path().display()))?;
+
+            InstallManifestFile {
+                size: metadata.len(),
+                sha256: get_file_sha256(entry.path())?,
+                link_target: None,
+            }
+        };
+
+        files.insert(get_manifest_key(relative_path), manifest_file);
+    }
+
+    Ok(InstallManifest { files })
+}
+
+/// Compares the current content of `folder` with `manifest`.
+pub fn verify_install_manifest(folder: &Path, manifest: &InstallManifest) -> Result<VerifyReport> {
+    let current = compute_install_manifest(folder)?;
+
+    let mut report = VerifyReport::default();
+
+    for (path, expected) in &manifest.files {
+        match current.files.get(path) {
+            Some(actual) if actual == expected => {}
+            Some(_) => report.modified.push(path.clone()),
+            None => report.missing.push(path.clone()),
+        }
+    }
+
+    report.extra = current
+        .files
+        .keys()
+        .filter(|path| !manifest.files.contains_key(*path))
+        .cloned()
+        .collect();
+
+    Ok(report)
+}
+
+pub fn save_install_manifest(fullversion: &str, manifest: &InstallManifest) -> Result<()> {
+    let path = get_manifest_path(fullversion)?;
+
+    std::fs::create_dir_all(path.parent().unwrap())
+        .with_context(|| format!("Failed to create folder for `{}`.", path.display()))?;
+
+    let file = File::create(&path)
+        .with_context(|| format!("Failed to create manifest file `{}`.", path.display()))?;
+
+    serde_json::to_writer_pretty(file, manifest)
+        .with_context(|| format!("Failed to write manifest file `{}`.", path.display()))?;
+
+    Ok(())
+}
+
+/// Returns `None` for versions that were installed before juliaup recorded
+/// manifests.
+pub fn load_install_manifest(fullversion: &str) -> Result<Option<InstallManifest>> {
+    let path = get_manifest_path(fullversion)?;
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to open manifest file `{}`.", path.display()))
+        }
+    };
+
+    let manifest = serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("Failed to parse manifest file `{}`.", path.display()))?;
+
+    Ok(Some(manifest))
+}
+
+pub fn remove_install_manifest(fullversion: &str) -> Result<()> {
+    let path = get_manifest_path(fullversion)?;
+
+    match std::fs::remove_file(&path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("Failed to delete manifest file `{}`.", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns the versions for which a manifest exists.
+pub fn list_install_manifests() -> Result<Vec<String>> {
+    let path = get_juliaup_manifests_path()?;
+
+    let entries = match std::fs::read_dir(&path) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("Failed to read folder `{}`.", path.display())),
+    };
+
+    let mut versions = Vec::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Failed to read folder `{}`.", path.display()))?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if let Some(version) = name.strip_prefix("julia-").and_then(|name| name.strip_suffix(".json")) {
+            versions.push(version.to_string());
+        }
+    }
+
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_install_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("bin")).unwrap();
+        std::fs::create_dir_all(dir.path().join("lib")).unwrap();
+        std::fs::write(dir.path().join("bin").join("julia"), "julia").unwrap();
+        std::fs::write(dir.path().join("lib").join("libjulia.so"), "libjulia").unwrap();
+        std::fs::write(dir.path().join("LICENSE.md"), "MIT").unwrap();
+
+        let manifest = compute_install_manifest(dir.path()).unwrap();
+        assert_eq!(manifest.files.len(), 3);
+        assert_eq!(manifest.files["bin/julia"].size, 5);
+        assert!(verify_install_manifest(dir.path(), &manifest).unwrap().is_ok());
+
+        std::fs::write(dir.path().join("bin").join("julia"), "virus").unwrap();
+        std::fs::remove_file(dir.path().join("LICENSE.md")).unwrap();
+        std::fs::write(dir.path().join("lib").join("extra.so"), "").unwrap();
+
+        let report = verify_install_manifest(dir.path(), &manifest).unwrap();
+        assert_eq!(report.modified, vec!["bin/julia"]);
+        assert_eq!(report.missing, vec!["LICENSE.md"]);
+        assert_eq!(report.extra, vec!["lib/extra.so"]);
+    }
+}
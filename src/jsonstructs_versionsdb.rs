@@ -4,7 +4,11 @@ use std::collections::HashMap;
 #[derive(Serialize, Deserialize)]
 pub struct JuliaupVersionDBVersion {
     #[serde(rename = "Url")]
-    pub url: String
+    pub url: String,
+    #[serde(rename = "Size", default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(rename = "Sha256", default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>
 }
 
 #[derive(Serialize, Deserialize)]
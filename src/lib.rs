@@ -2,6 +2,12 @@ pub mod utils;
 pub mod jsonstructs_versionsdb;
 pub mod config_file;
 pub mod versions_file;
+pub mod progress;
+pub mod output;
+pub mod http_client;
+pub mod download_cache;
+pub mod archive;
+pub mod install_manifest;
 pub mod operations;
 pub mod command_add;
 pub mod command_default;
@@ -11,6 +17,19 @@ pub mod command_status;
 pub mod command_remove;
 pub mod command_update;
 pub mod command_initial_setup_from_launcher;
+pub mod command_cache;
+pub mod command_verify;
+pub mod command_info;
+pub mod command_list;
+pub mod command_which;
+pub mod command_run;
+pub mod command_matrix;
+pub mod command_self;
+pub mod command_init;
+pub mod command_completions;
+pub mod command_env;
+pub mod command_rename;
+pub mod command_copy;
 
 include!(concat!(env!("OUT_DIR"), "/bundled_version.rs"));
 
@@ -1,60 +1,450 @@
+use crate::archive::{detect_archive_format, extract_archive_sans_parent, get_format_from_url, ArchiveFormat};
 use crate::config_file::JuliaupConfig;
 use crate::config_file::JuliaupConfigChannel;
 use crate::config_file::JuliaupConfigVersion;
+use crate::download_cache::{download_to_cache, get_cached_download, remove_cached_file, CachedDownload};
+use crate::http_client::HttpClient;
+use crate::install_manifest::{compute_install_manifest, load_install_manifest, remove_install_manifest, save_install_manifest};
 use crate::jsonstructs_versionsdb::JuliaupVersionDB;
+use crate::progress::{report_info, report_status, Progress, ProgressGroup};
 use crate::utils::get_juliaup_home_path;
-use crate::utils::parse_versionstring;
-use anyhow::{anyhow, Context, Result};
-use console::style;
-use flate2::read::GzDecoder;
-use indicatif::{ProgressBar, ProgressStyle};
+use crate::utils::{check_url_channel_name, parse_versionstring, verify_file_sha256};
+use anyhow::{anyhow, bail, Context, Result};
+use indicatif::HumanBytes;
+use normpath::PathExt;
+use semver::Version;
 use std::{
     io::Read,
-    path::{Component::Normal, Path, PathBuf},
+    path::{Path, PathBuf},
+    process::{Output, Stdio},
+    sync::Mutex,
+    time::{Duration, Instant},
 };
-use tar::Archive;
-
-fn unpack_sans_parent<R, P>(mut archive: Archive<R>, dst: P) -> Result<()>
-where
-    R: Read,
-    P: AsRef<Path>,
-{
-    for entry in archive.entries()? {
-        let mut entry = entry?;
-        let path: PathBuf = entry
-            .path()?
-            .components()
-            .skip(1) // strip top-level directory
-            .filter(|c| matches!(c, Normal(_))) // prevent traversal attacks TODO We should actually abort if we come across a non-standard path element
-            .collect();
-        entry.unpack(dst.as_ref().join(path))?;
+
+const SMOKE_TEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn download_extract_sans_parent(
+    client: &HttpClient,
+    url: &str,
+    sha256: Option<&str>,
+    target_path: &Path,
+    pb: &Progress,
+) -> Result<()> {
+    let archive_path = download_to_cache(client, url, sha256, pb)?;
+
+    let result = detect_archive_format(url, &archive_path)
+        .and_then(|format| extract_archive_sans_parent(&archive_path, format, target_path));
+
+    if let Err(err) = result {
+        // A cached file that can't be extracted is most likely corrupt, so we make
+        // sure the next attempt downloads it again.
+        let _ = remove_cached_file(&archive_path);
+        return Err(err)
+            .with_context(|| format!("Failed to extract downloaded file from url `{}`.", url));
     }
     Ok(())
 }
 
-fn download_extract_sans_parent(url: &String, target_path: &Path) -> Result<()> {
-    let response = ureq::get(url)
-        .call()
-        .with_context(|| format!("Failed to download from url `{}`.", url))?;
+fn get_staging_foldername(target_foldername: &str) -> String {
+    format!(".{}.staging", target_foldername)
+}
+
+fn remove_dir_if_exists(path: &Path) -> Result<()> {
+    if path.exists() {
+        std::fs::remove_dir_all(path)
+            .with_context(|| format!("Failed to delete folder `{}`.", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Runs `bin/julia --version` in `julia_folder` and checks that it reports
+/// the version we installed. This catches downloads that extract fine but
+/// can't run on this system, e.g. because of a wrong architecture. For
+/// versions installed from a url we don't know the version, so we only check
+/// that Julia starts.
+pub fn smoke_test_julia(julia_folder: &Path, fullversion: &str) -> Result<()> {
+    smoke_test_julia_with_timeout(julia_folder, fullversion, SMOKE_TEST_TIMEOUT)
+}
+
+// Runs `command` like `Command::output`, but kills it if it hasn't finished
+// after `timeout` and returns `None` in that case.
+fn output_with_timeout(command: &mut std::process::Command, timeout: Duration) -> std::io::Result<Option<Output>> {
+    let mut child = command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+
+    let read_to_end = |mut pipe: Box<dyn Read + Send>| {
+        std::thread::spawn(move || {
+            let mut buffer = Vec::new();
+            let _ = pipe.read_to_end(&mut buffer);
+            buffer
+        })
+    };
+    let stdout_reader = read_to_end(Box::new(child.stdout.take().unwrap())); // unwrap OK because stdout is piped
+    let stderr_reader = read_to_end(Box::new(child.stderr.take().unwrap())); // unwrap OK because stderr is piped
+
+    let deadline = Instant::now() + timeout;
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            // The readers are left behind, they only finish once everything
+            // that inherited the pipes has exited.
+            return Ok(None);
+        }
+
+        std::thread::sleep(Duration::from_millis(20));
+    };
+
+    Ok(Some(Output {
+        status,
+        stdout: stdout_reader.join().unwrap_or_default(),
+        stderr: stderr_reader.join().unwrap_or_default(),
+    }))
+}
+
+fn smoke_test_julia_with_timeout(julia_folder: &Path, fullversion: &str, timeout: Duration) -> Result<()> {
+    let expected_version = parse_versionstring(&fullversion.to_string()).ok().map(|(_, version)| version);
+
+    let julia_path = julia_folder
+        .join("bin")
+        .join(format!("julia{}", std::env::consts::EXE_SUFFIX));
 
-    let content_length = response.header("Content-Length").and_then(|v| v.parse::<u64>().ok() );
+    let output = output_with_timeout(std::process::Command::new(&julia_path).arg("--version"), timeout)
+        .with_context(|| format!("Failed to start `{}` to check the new Julia installation.", julia_path.display()))?
+        .ok_or_else(|| anyhow!(
+            "The newly installed Julia {} did not finish `julia --version` within {} seconds and was stopped.",
+            fullversion,
+            timeout.as_secs()
+        ))?;
 
-    let pb = match content_length {
-        Some(content_length) => ProgressBar::new(content_length),
-        None => ProgressBar::new_spinner(),
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    if !output.status.success() {
+        bail!(
+            "The newly installed Julia {} failed to start ({}). Its output was:\n{}{}",
+            fullversion,
+            output.status,
+            stdout,
+            stderr
+        );
+    }
+
+    let expected_version = match expected_version {
+        Some(expected_version) => expected_version,
+        None => return Ok(()),
     };
-    
-    pb.set_prefix("  Downloading:");
-    pb.set_style(ProgressStyle::default_bar()
-    .template("{prefix:.cyan.bold} [{bar}] {bytes}/{total_bytes} eta: {eta}")
-                .progress_chars("=> "));
-
-    let foo = pb.wrap_read(response.into_reader());
-
-    let tar = GzDecoder::new(foo);
-    let archive = Archive::new(tar);
-    unpack_sans_parent(archive, &target_path)
-        .with_context(|| format!("Failed to extract downloaded file from url `{}`.", url))?;
+
+    let reported_version = stdout.trim().trim_start_matches("julia version ");
+
+    match Version::parse(reported_version) {
+        Ok(version)
+            if version.major == expected_version.major
+                && version.minor == expected_version.minor
+                && version.patch == expected_version.patch
+                && version.pre == expected_version.pre => Ok(()),
+        _ => bail!(
+            "The newly installed Julia was expected to be version {}, but `julia --version` reported `{}`.",
+            expected_version,
+            stdout.trim()
+        ),
+    }
+}
+
+// Creates the version folder for `fullversion` by running `populate` on an
+// empty staging folder, which is only moved into place once it is complete.
+fn install_version_folder(
+    fullversion: &str,
+    smoke_test: bool,
+    populate: impl FnOnce(&Path) -> Result<()>,
+) -> Result<JuliaupConfigVersion> {
+    let child_target_foldername = format!("julia-{}", fullversion);
+
+    let home_path = get_juliaup_home_path()
+        .with_context(|| "Failed to retrieve juliap folder while trying to install new version.")?;
+
+    let target_path = home_path.join(&child_target_foldername);
+    let staging_path = home_path.join(get_staging_foldername(&child_target_foldername));
+
+    // A staging folder that still exists at this point was left behind by an
+    // earlier install attempt that did not finish, so it is safe to discard.
+    remove_dir_if_exists(&staging_path)?;
+
+    std::fs::create_dir_all(&staging_path)
+        .with_context(|| format!("Failed to create staging folder `{}`.", staging_path.display()))?;
+
+    // The manifest is computed before the smoke test runs, so that anything
+    // Julia writes on its first start shows up when verifying.
+    let result = populate(&staging_path)
+        .and_then(|_| compute_install_manifest(&staging_path))
+        .and_then(|manifest| {
+            if smoke_test {
+                smoke_test_julia(&staging_path, fullversion)?;
+            }
+            Ok(manifest)
+        });
+
+    // The manifest is saved before the folder is moved into place, so that
+    // there never is a version folder without its manifest.
+    let result = result.and_then(|manifest| save_install_manifest(fullversion, &manifest));
+
+    if let Err(err) = result {
+        // Best effort cleanup, the next install attempt will try again.
+        let _ = std::fs::remove_dir_all(&staging_path);
+        return Err(err);
+    }
+
+    // The target folder exists here either because it is not tracked in the
+    // configuration file, in which case its content can't be trusted, or
+    // because `juliaup verify` is reinstalling a damaged version. Either way
+    // the new files replace it.
+    let result = remove_dir_if_exists(&target_path).and_then(|_| {
+        std::fs::rename(&staging_path, &target_path).with_context(|| {
+            format!(
+                "Failed to move staging folder `{}` to `{}`.",
+                staging_path.display(),
+                target_path.display()
+            )
+        })
+    });
+
+    if let Err(err) = result {
+        let _ = remove_install_manifest(fullversion);
+        let _ = std::fs::remove_dir_all(&staging_path);
+        return Err(err);
+    }
+
+    let mut rel_path = PathBuf::new();
+    rel_path.push(".");
+    rel_path.push(&child_target_foldername);
+
+    Ok(JuliaupConfigVersion {
+        path: rel_path.to_string_lossy().into_owned(),
+        from_file: None,
+    })
+}
+
+/// Downloads and extracts `fullversion` into the juliaup folder and returns
+/// the entry that needs to be added to `installed_versions` for it. This does
+/// not touch the configuration, so it can run for several versions at once.
+/// If `smoke_test` is set, the new version is only kept if it starts.
+pub fn install_version_files(
+    fullversion: &str,
+    version_db: &JuliaupVersionDB,
+    client: &HttpClient,
+    pb: &Progress,
+    smoke_test: bool,
+) -> Result<JuliaupConfigVersion> {
+    let db_version = version_db
+        .available_versions
+        .get(fullversion)
+        .ok_or_else(|| anyhow!(
+            "Failed to find download url in versions db for '{}'.",
+            fullversion
+        ))?;
+
+    install_version_folder(fullversion, smoke_test, |staging_path| {
+        download_extract_sans_parent(client, &db_version.url, db_version.sha256.as_deref(), staging_path, pb)
+    })
+}
+
+// Archives and folders from julialang.org are called like
+// `julia-1.6.2-linux-x86_64.tar.gz` or `julia-1.6.2`. Other names are not
+// checked.
+fn get_version_from_file_name(path: &Path) -> Option<Version> {
+    let file_name = path.file_name()?.to_str()?;
+    let version = file_name.strip_prefix("julia-")?.split('-').next()?;
+
+    Version::parse(version).ok()
+}
+
+/// Installs `fullversion` from a local archive or from a folder that
+/// contains an extracted Julia, instead of downloading it. Archives are
+/// checked against `sha256`, or else the checksum in the versions db if it
+/// has one.
+pub fn install_version_from_file(
+    fullversion: &String,
+    source_path: &Path,
+    sha256: Option<&str>,
+    config_data: &mut JuliaupConfig,
+    version_db: &JuliaupVersionDB,
+    smoke_test: bool,
+) -> Result<()> {
+    let db_version = version_db
+        .available_versions
+        .get(fullversion)
+        .ok_or_else(|| anyhow!("Failed to find '{}' in the versions db.", fullversion))?;
+
+    let (platform, version) = parse_versionstring(fullversion).with_context(String::new)?;
+
+    if let Some(file_version) = get_version_from_file_name(source_path) {
+        if (file_version.major, file_version.minor, file_version.patch) != (version.major, version.minor, version.patch) {
+            bail!(
+                "`{}` looks like Julia {}, but Julia {} is needed.",
+                source_path.display(),
+                file_version,
+                fullversion
+            );
+        }
+    }
+
+    // Like `install_version`, an installed version is shared with the new
+    // channel.
+    if config_data.installed_versions.contains_key(fullversion) {
+        report_info(format!("Julia {} is already installed, `{}` is not used.", fullversion, source_path.display()));
+        return Ok(());
+    }
+
+    let metadata = std::fs::metadata(source_path)
+        .with_context(|| format!("Failed to read `{}`.", source_path.display()))?;
+
+    report_status(
+        "Installing",
+        format!("Julia {} ({}) from `{}`.", version, platform, source_path.display()),
+    );
+
+    let mut installed_version = if metadata.is_dir() {
+        if sha256.is_some() {
+            bail!("`{}` is a folder, `--sha256` can only check archives.", source_path.display());
+        }
+
+        ensure_disk_space(get_folder_size(source_path), fullversion)?;
+
+        install_version_folder(fullversion, smoke_test, |staging_path| {
+            let mut options = fs_extra::dir::CopyOptions::new();
+            options.content_only = true;
+
+            fs_extra::dir::copy(source_path, staging_path, &options)
+                .map(|_| ())
+                .with_context(|| format!("Failed to copy `{}`.", source_path.display()))
+        })?
+    } else {
+        match sha256.or(db_version.sha256.as_deref()) {
+            Some(sha256) => verify_file_sha256(source_path, sha256)?,
+            None => report_info(format!("  No checksum for Julia {} was given with `--sha256`, skipping the checksum verification.", version)),
+        }
+
+        let format = detect_archive_format(&source_path.to_string_lossy(), source_path)?;
+
+        ensure_disk_space(metadata.len() * format.expansion_factor(), fullversion)?;
+
+        install_version_folder(fullversion, smoke_test, |staging_path| {
+            extract_archive_sans_parent(source_path, format, staging_path)
+                .with_context(|| format!("Failed to extract `{}`.", source_path.display()))
+        })?
+    };
+
+    let source_path = source_path.normalize().map(|path| path.into_path_buf()).unwrap_or_else(|_| source_path.to_path_buf());
+    installed_version.from_file = Some(source_path.to_string_lossy().into_owned());
+
+    config_data
+        .installed_versions
+        .insert(fullversion.clone(), installed_version);
+
+    Ok(())
+}
+
+// Returns the size of the archive of `fullversion` and how much of it still
+// needs to be downloaded, or `None` if we can't tell.
+fn get_archive_sizes(
+    fullversion: &str,
+    version_db: &JuliaupVersionDB,
+    client: &HttpClient,
+) -> Result<Option<(u64, u64)>> {
+    let db_version = match version_db.available_versions.get(fullversion) {
+        Some(db_version) => db_version,
+        None => return Ok(None),
+    };
+
+    match get_cached_download(&db_version.url)? {
+        CachedDownload::Complete(size) => Ok(Some((size, 0))),
+        cached => {
+            let partial_size = match cached {
+                CachedDownload::Partial(size) => size,
+                _ => 0,
+            };
+
+            Ok(db_version
+                .size
+                .or_else(|| client.get_content_length(&db_version.url))
+                .map(|size| (size, size.saturating_sub(partial_size))))
+        }
+    }
+}
+
+// Returns how many bytes installing `fullversion` needs on disk, or `None`
+// if the size of the download can't be determined.
+fn estimate_required_space(
+    fullversion: &str,
+    version_db: &JuliaupVersionDB,
+    client: &HttpClient,
+) -> Result<Option<u64>> {
+    let expansion_factor = match version_db.available_versions.get(fullversion) {
+        Some(db_version) => get_format_from_url(&db_version.url)
+            .unwrap_or(ArchiveFormat::TarGz)
+            .expansion_factor(),
+        None => return Ok(None),
+    };
+
+    Ok(get_archive_sizes(fullversion, version_db, client)?
+        .map(|(archive_size, download_size)| download_size + archive_size * expansion_factor))
+}
+
+/// Returns how many bytes need to be downloaded to install `fullversion`,
+/// which is zero if the archive is in the download cache, or `None` if we
+/// can't tell.
+pub fn get_download_size(
+    fullversion: &str,
+    version_db: &JuliaupVersionDB,
+    client: &HttpClient,
+) -> Result<Option<u64>> {
+    Ok(get_archive_sizes(fullversion, version_db, client)?.map(|(_, download_size)| download_size))
+}
+
+/// Makes sure that the file system of the juliaup folder has enough free
+/// space to install all of `fullversions`, so that we fail with a clear
+/// message before downloading anything.
+pub fn check_disk_space(
+    fullversions: &[String],
+    version_db: &JuliaupVersionDB,
+    client: &HttpClient,
+) -> Result<()> {
+    let mut required_space = 0;
+    for fullversion in fullversions {
+        match estimate_required_space(fullversion, version_db, client)? {
+            Some(size) => required_space += size,
+            None => return Ok(()), // We don't block an install on a guess we can't make
+        }
+    }
+
+    ensure_disk_space(required_space, &fullversions.join(", "))
+}
+
+fn ensure_disk_space(required_space: u64, fullversions: &str) -> Result<()> {
+    let home_path = get_juliaup_home_path()
+        .with_context(|| "Failed to retrieve juliap folder while checking the available disk space.")?;
+
+    std::fs::create_dir_all(&home_path)
+        .with_context(|| format!("Failed to create folder `{}`.", home_path.display()))?;
+
+    let available_space = fs2::available_space(&home_path).with_context(|| {
+        format!("Failed to determine the available disk space for `{}`.", home_path.display())
+    })?;
+
+    if available_space < required_space {
+        bail!(
+            "Not enough disk space to install Julia {}: about {} are needed, but only {} are available in `{}`. You can free up space by running `juliaup gc` or by removing channels you no longer need with `juliaup remove`.",
+            fullversions,
+            HumanBytes(required_space),
+            HumanBytes(available_space),
+            home_path.display()
+        );
+    }
+
     Ok(())
 }
 
@@ -62,78 +452,618 @@ pub fn install_version(
     fullversion: &String,
     config_data: &mut JuliaupConfig,
     version_db: &JuliaupVersionDB,
+    smoke_test: bool,
 ) -> Result<()> {
     // Return immediately if the version is already installed.
     if config_data.installed_versions.contains_key(fullversion) {
         return Ok(());
     }
 
-    let download_url = version_db
-        .available_versions
+    let (platform, version) = parse_versionstring(fullversion).with_context(String::new)?;
+
+    report_status("Installing", format!("Julia {} ({}).", version, platform));
+
+    let client = HttpClient::from_settings(&config_data.settings)?;
+
+    check_disk_space(std::slice::from_ref(fullversion), version_db, &client)?;
+
+    let pb = Progress::new_download("  Downloading:");
+
+    let installed_version = install_version_files(fullversion, version_db, &client, &pb, smoke_test)?;
+
+    config_data
+        .installed_versions
+        .insert(fullversion.clone(), installed_version);
+
+    Ok(())
+}
+
+/// Like `install_version_files`, but for an archive at `url` that is not in
+/// the versions db. The archive is checked against `sha256` if given.
+pub fn install_url_version_files(
+    fullversion: &str,
+    url: &str,
+    sha256: Option<&str>,
+    client: &HttpClient,
+    pb: &Progress,
+    smoke_test: bool,
+) -> Result<JuliaupConfigVersion> {
+    install_version_folder(fullversion, smoke_test, |staging_path| {
+        download_extract_sans_parent(client, url, sha256, staging_path, pb)
+    })
+}
+
+/// Installs the archive at `url` as `fullversion`, for channels that are not
+/// in the versions db.
+pub fn install_version_from_url(
+    fullversion: &String,
+    url: &str,
+    sha256: Option<&str>,
+    config_data: &mut JuliaupConfig,
+    smoke_test: bool,
+) -> Result<()> {
+    // Return immediately if the version is already installed.
+    if config_data.installed_versions.contains_key(fullversion) {
+        return Ok(());
+    }
+
+    report_status("Installing", format!("Julia from `{}`.", url));
+
+    let client = HttpClient::from_settings(&config_data.settings)?;
+
+    let pb = Progress::new_download("  Downloading:");
+
+    let installed_version = install_url_version_files(fullversion, url, sha256, &client, &pb, smoke_test)?;
+
+    config_data
+        .installed_versions
+        .insert(fullversion.clone(), installed_version);
+
+    Ok(())
+}
+
+fn get_installed_version_path(fullversion: &str, config_data: &JuliaupConfig) -> Result<PathBuf> {
+    let detail = config_data
+        .installed_versions
         .get(fullversion)
-        .ok_or(anyhow!(
-            "Failed to find download url in versions db for '{}'.",
-            fullversion
-        ))?
-        .url
-        .clone();
+        .ok_or_else(|| anyhow!("Julia {} is not installed.", fullversion))?;
 
-    let (platform, version) = parse_versionstring(fullversion).with_context(|| format!(""))?;
+    let home_path = get_juliaup_home_path()
+        .with_context(|| "Failed to retrieve juliap folder while trying to find an installed version.")?;
 
-    let child_target_foldername = format!("julia-{}", fullversion);
+    Ok(home_path.join(&detail.path))
+}
 
-    let target_path = get_juliaup_home_path()
-        .with_context(|| "Failed to retrieve juliap folder while trying to install new version.")?
-        .join(&child_target_foldername);
+/// Installs a copy of the installed version `fullversion` as
+/// `new_fullversion`. Channels installed from a url each need their own
+/// version, so this is how they are copied.
+pub fn copy_installed_version(fullversion: &str, new_fullversion: &str, config_data: &mut JuliaupConfig) -> Result<()> {
+    let source_path = get_installed_version_path(fullversion, config_data)?;
 
-    std::fs::create_dir_all(target_path.parent().unwrap())?;
+    let installed_version = install_version_folder(new_fullversion, false, |staging_path| {
+        let mut options = fs_extra::dir::CopyOptions::new();
+        options.content_only = true;
 
-    eprintln!("{} Julia {} ({}).", style("Installing").green().bold(), version, platform);
+        fs_extra::dir::copy(&source_path, staging_path, &options)
+            .map(|_| ())
+            .with_context(|| format!("Failed to copy `{}`.", source_path.display()))
+    })?;
 
-    download_extract_sans_parent(&download_url, &target_path)?;
+    config_data
+        .installed_versions
+        .insert(new_fullversion.to_string(), installed_version);
 
-    let mut rel_path = PathBuf::new();
-    rel_path.push(".");
-    rel_path.push(&child_target_foldername);
+    Ok(())
+}
+
+/// Checks that the installed `channel` can be renamed or copied to
+/// `new_channel` and returns its configuration. `action` says what happens
+/// to the channel in the error messages, i.e. `renamed` or `copied`.
+pub fn check_channel_can_move(
+    config_data: &JuliaupConfig,
+    version_db: &JuliaupVersionDB,
+    channel: &str,
+    new_channel: &str,
+    action: &str,
+) -> Result<JuliaupConfigChannel> {
+    let channel_data = match config_data.installed_channels.get(channel) {
+        None => bail!("'{}' cannot be {} because it is currently not installed.", channel, action),
+        // The name of a system channel decides which Julia versions `update` installs.
+        Some(JuliaupConfigChannel::SystemChannel { version: _ }) => bail!(
+            "'{}' cannot be {} because it is a system channel, use `juliaup add` to install other system channels.",
+            channel,
+            action
+        ),
+        Some(channel_data) => channel_data.clone(),
+    };
+
+    if config_data.installed_channels.contains_key(new_channel) {
+        bail!("Channel name `{}` is already used.", new_channel)
+    }
+
+    // The version and the folder of a channel installed from a url are named
+    // after the channel.
+    if let JuliaupConfigChannel::DirectDownloadChannel { .. } = channel_data {
+        check_url_channel_name(new_channel)?;
+    }
+
+    if version_db.available_channels.contains_key(new_channel) {
+        eprintln!("WARNING: The channel name `{}` is also a system channel. The {} channel will hide this system channel.", new_channel, action);
+    }
+
+    Ok(channel_data)
+}
+
+/// Moves the installed version `fullversion`, its folder and its manifest,
+/// to `new_fullversion`.
+pub fn rename_installed_version(fullversion: &str, new_fullversion: &str, config_data: &mut JuliaupConfig) -> Result<()> {
+    let source_path = get_installed_version_path(fullversion, config_data)?;
+
+    let child_target_foldername = format!("julia-{}", new_fullversion);
+    let target_path = source_path.with_file_name(&child_target_foldername);
+
+    let manifest = load_install_manifest(fullversion)?;
+
+    // Like in `install_version_folder`, a folder that is not tracked in the
+    // configuration file can't be trusted.
+    remove_dir_if_exists(&target_path)?;
+
+    std::fs::rename(&source_path, &target_path).with_context(|| {
+        format!("Failed to move `{}` to `{}`.", source_path.display(), target_path.display())
+    })?;
+
+    if let Some(manifest) = manifest {
+        // Without its manifest the moved folder could not be verified, so it
+        // goes back to where the configuration file expects it.
+        if let Err(err) = save_install_manifest(new_fullversion, &manifest) {
+            if let Err(rollback_err) = std::fs::rename(&target_path, &source_path) {
+                eprintln!("WARNING: Failed to move `{}` back to `{}`: {}", target_path.display(), source_path.display(), rollback_err);
+            }
+
+            return Err(err);
+        }
 
+        if let Err(err) = remove_install_manifest(fullversion) {
+            eprintln!("WARNING: Failed to delete the file manifest of Julia {}: {:#}", fullversion, err);
+        }
+    }
+
+    let from_file = config_data.installed_versions.remove(fullversion).and_then(|detail| detail.from_file);
     config_data.installed_versions.insert(
-        fullversion.clone(),
+        new_fullversion.to_string(),
         JuliaupConfigVersion {
-            path: rel_path.to_string_lossy().into_owned(),
+            path: Path::new(".").join(&child_target_foldername).to_string_lossy().into_owned(),
+            from_file,
         },
     );
 
     Ok(())
 }
 
-pub fn garbage_collect_versions(config_data: &mut JuliaupConfig) -> Result<()> {
+// `ProgressGroup::join` only returns once every bar is finished or abandoned,
+// so a worker must not leave a bar behind, not even when it panics.
+struct AbandonOnDrop<'a> {
+    pb: &'a Progress,
+    finished: bool,
+}
+
+impl Drop for AbandonOnDrop<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.pb.abandon();
+        }
+    }
+}
+
+// If a worker panics, the versions it would have picked up next are never
+// started, so their bars are abandoned as well.
+struct AbandonPendingOnPanic<'a>(&'a Mutex<std::vec::IntoIter<(String, Progress)>>);
+
+impl Drop for AbandonPendingOnPanic<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            let mut pending = self.0.lock().unwrap_or_else(|err| err.into_inner());
+            for (_, pb) in pending.by_ref() {
+                pb.abandon();
+            }
+        }
+    }
+}
+
+/// Runs `install_version_files` for all `fullversions`, with at most
+/// `max_parallel` of them at the same time. Each version gets its own progress
+/// bar and a failure for one version does not stop the others.
+pub fn install_versions_parallel(
+    fullversions: &[String],
+    version_db: &JuliaupVersionDB,
+    client: &HttpClient,
+    max_parallel: usize,
+    smoke_test: bool,
+) -> Vec<(String, Result<JuliaupConfigVersion>)> {
+    if fullversions.is_empty() {
+        return Vec::new();
+    }
+
+    if let Err(err) = check_disk_space(fullversions, version_db, client) {
+        let message = format!("{:#}", err);
+        return fullversions
+            .iter()
+            .map(|fullversion| (fullversion.clone(), Err(anyhow!("{}", message))))
+            .collect();
+    }
+
+    let progress_group = ProgressGroup::new();
+
+    let pending = Mutex::new(
+        fullversions
+            .iter()
+            .map(|fullversion| {
+                let pb = progress_group.add_download(&format!("  {}:", fullversion));
+                (fullversion.clone(), pb)
+            })
+            .collect::<Vec<_>>()
+            .into_iter(),
+    );
+    let results = Mutex::new(Vec::new());
+
+    std::thread::scope(|scope| {
+        for _ in 0..max_parallel.clamp(1, fullversions.len()) {
+            scope.spawn(|| {
+                let _pending_guard = AbandonPendingOnPanic(&pending);
+
+                loop {
+                    let next = pending.lock().unwrap().next();
+                    let (fullversion, pb) = match next {
+                        Some(next) => next,
+                        None => break,
+                    };
+
+                    let mut pb_guard = AbandonOnDrop { pb: &pb, finished: false };
+
+                    let result = install_version_files(&fullversion, version_db, client, &pb, smoke_test);
+
+                    if result.is_ok() {
+                        pb.finish();
+                        pb_guard.finished = true;
+                    }
+                    drop(pb_guard);
+
+                    results.lock().unwrap().push((fullversion, result));
+                }
+            });
+        }
+
+        progress_group.join();
+    });
+
+    results.into_inner().unwrap()
+}
+
+/// Returns the installed versions that no channel uses anymore.
+pub fn get_unused_versions(config_data: &JuliaupConfig) -> Vec<String> {
+    config_data
+        .installed_versions
+        .keys()
+        .filter(|installed_version| {
+            config_data.installed_channels.values().all(|channel| match channel {
+                JuliaupConfigChannel::SystemChannel { version } => &version != installed_version,
+                JuliaupConfigChannel::DirectDownloadChannel { version, url: _, sha256: _ } => {
+                    &version != installed_version
+                }
+                JuliaupConfigChannel::LinkedChannel {
+                    command: _,
+                    args: _,
+                } => true,
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+/// Returns the number of bytes used by the files in `path`. Files that
+/// can't be read are skipped.
+pub fn get_folder_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Deletes all versions that no channel uses anymore and returns how many
+/// bytes were freed. Folders that can't be deleted are dropped from the
+/// configuration anyway, `juliaup gc` picks them up later as orphaned folders.
+pub fn garbage_collect_versions(config_data: &mut JuliaupConfig) -> Result<u64> {
     let home_path = get_juliaup_home_path().with_context(|| {
         "Failed to retrieve juliap folder while trying to garbage collect versions."
     })?;
 
-    let mut versions_to_uninstall: Vec<String> = Vec::new();
-    for (installed_version, detail) in &config_data.installed_versions {
-        if config_data.installed_channels.iter().all(|j| match &j.1 {
-            JuliaupConfigChannel::SystemChannel { version } => version != installed_version,
-            JuliaupConfigChannel::LinkedChannel {
-                command: _,
-                args: _,
-            } => true,
-        }) {
+    let mut freed_space = 0;
+
+    for installed_version in get_unused_versions(config_data) {
+        if let Some(detail) = config_data.installed_versions.remove(&installed_version) {
             let path_to_delete = home_path.join(&detail.path);
-            let display = path_to_delete.display();
+            let size = get_folder_size(&path_to_delete);
 
             match std::fs::remove_dir_all(&path_to_delete) {
-            Err(_) => eprintln!("WARNING: Failed to delete {}. You can try to delete at a later point by running `juliaup gc`.", display),
-            Ok(_) => ()
-        };
-            versions_to_uninstall.push(installed_version.clone());
+                Ok(()) => freed_space += size,
+                Err(_) => eprintln!(
+                    "WARNING: Failed to delete {}. You can try to delete at a later point by running `juliaup gc`.",
+                    path_to_delete.display()
+                ),
+            }
+        }
+
+        if let Err(err) = remove_install_manifest(&installed_version) {
+            eprintln!("WARNING: Failed to delete the file manifest of Julia {}: {:#}", installed_version, err);
         }
     }
 
-    for i in versions_to_uninstall {
-        config_data.installed_versions.remove(&i);
+    Ok(freed_space)
+}
+
+/// Returns all `julia-*` folders in the juliaup folder that are not tracked
+/// in the configuration, as well as staging folders left behind by install
+/// attempts that did not finish.
+pub fn find_orphaned_folders(config_data: &JuliaupConfig) -> Result<Vec<PathBuf>> {
+    let home_path = get_juliaup_home_path().with_context(|| {
+        "Failed to retrieve juliap folder while trying to find orphaned folders."
+    })?;
+
+    let tracked_folders: Vec<PathBuf> = config_data
+        .installed_versions
+        .values()
+        .map(|detail| home_path.join(&detail.path))
+        .collect();
+
+    let entries = match std::fs::read_dir(&home_path) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to read folder `{}`.", home_path.display()))
+        }
+    };
+
+    let mut orphaned_folders = Vec::new();
+
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Failed to read folder `{}`.", home_path.display()))?;
+
+        if !entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false) {
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let is_version_folder = name.starts_with("julia-");
+        let is_staging_folder = name.starts_with(".julia-") && name.ends_with(".staging");
+
+        if is_staging_folder || (is_version_folder && !tracked_folders.iter().any(|tracked| tracked == &home_path.join(&name))) {
+            orphaned_folders.push(entry.path());
+        }
     }
 
-    Ok(())
+    orphaned_folders.sort();
+
+    Ok(orphaned_folders)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum JuliaupInvalidChannel {
+    #[error("Invalid channel specified")]
+    FromCmdLine(),
+}
+
+fn get_julia_path_from_version(
+    config_data: &JuliaupConfig,
+    channel: &str,
+    version: &str,
+    juliaupconfig_path: &Path,
+) -> Result<(PathBuf, Vec<String>)> {
+    let path = &config_data
+        .installed_versions.get(version)
+        .ok_or_else(|| anyhow!("The juliaup configuration is in an inconsistent state, the channel {} is pointing to Julia version {}, which is not installed.", channel, version))?.path;
+
+    let absolute_path = juliaupconfig_path
+        .parent()
+        .unwrap() // unwrap OK because there should always be a parent
+        .join(path)
+        .join("bin")
+        .join(format!("julia{}", std::env::consts::EXE_SUFFIX))
+        .normalize()
+        .with_context(|| {
+            format!(
+                "Failed to normalize path for Julia binary, starting from `{}`.",
+                juliaupconfig_path.display()
+            )
+        })?;
+    Ok((absolute_path.into_path_buf(), Vec::new()))
+}
+
+/// Returns the Julia binary and the arguments that need to be passed to it
+/// for `channel`. This is what the launcher starts for `julia +channel`.
+/// `julia_version_from_cmd_line` says whether the user picked the channel
+/// or whether it is the configured default.
+pub fn get_julia_path_from_channel(
+    config_data: &JuliaupConfig,
+    channel: &str,
+    juliaupconfig_path: &Path,
+    julia_version_from_cmd_line: bool,
+) -> Result<(PathBuf, Vec<String>)> {
+    let channel_info = if julia_version_from_cmd_line {
+        config_data
+            .installed_channels
+            .get(channel)
+            .ok_or(JuliaupInvalidChannel::FromCmdLine {})? // TODO #115 Handle this better in the main function
+    } else {
+        config_data.installed_channels.get(channel)
+            .ok_or_else(|| anyhow!("The juliaup configuration is in an inconsistent state, the currently configured default channel `{}` is not installed.", channel))?
+    };
+
+    match channel_info {
+        JuliaupConfigChannel::LinkedChannel { command, args } => {
+            Ok((
+                PathBuf::from(command),
+                args.as_ref().map_or_else(Vec::new, |v| v.clone()),
+            ))
+        }
+        JuliaupConfigChannel::SystemChannel { version }
+        | JuliaupConfigChannel::DirectDownloadChannel { version, url: _, sha256: _ } => {
+            get_julia_path_from_version(config_data, channel, version, juliaupconfig_path)
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config_file::JuliaupConfigSettings;
+    use crate::jsonstructs_versionsdb::JuliaupVersionDBVersion;
+    use crate::utils::{get_file_sha256, use_test_juliaup_home};
+    use std::collections::HashMap;
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn empty_config() -> JuliaupConfig {
+        JuliaupConfig {
+            default: None,
+            installed_versions: HashMap::new(),
+            installed_channels: HashMap::new(),
+            settings: JuliaupConfigSettings::default(),
+        }
+    }
+
+    fn version_db_with(fullversion: &str) -> JuliaupVersionDB {
+        let mut available_versions = HashMap::new();
+        available_versions.insert(
+            fullversion.to_string(),
+            JuliaupVersionDBVersion { url: "https://example.com/julia.tar.gz".to_string(), size: None, sha256: None },
+        );
+
+        JuliaupVersionDB { available_versions, available_channels: HashMap::new() }
+    }
+
+    fn fake_julia_archive(script: &str) -> Vec<u8> {
+        let source = write_fake_julia(script);
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+        builder.append_dir_all("julia-1.6.2", source.path()).unwrap();
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    // Serves `payload` for every request and returns the url of a file with
+    // the same name as the official Julia 1.6.2 archive.
+    fn serve_archive(payload: Vec<u8>) -> String {
+        let (url, _) = crate::http_client::serve_http("/bin/julia-1.6.2-linux-x86_64.tar.gz", move |stream, _, _| {
+            let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", payload.len());
+            let _ = stream.write_all(&payload);
+        });
+
+        url
+    }
+
+    fn assert_installed(config_data: &JuliaupConfig, fullversion: &str) {
+        let home_path = get_juliaup_home_path().unwrap();
+        let julia_path = home_path.join(&config_data.installed_versions[fullversion].path).join("bin").join("julia");
+
+        assert!(julia_path.is_file());
+        assert!(load_install_manifest(fullversion).unwrap().is_some());
+    }
+
+    fn write_fake_julia(script: &str) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let julia_path = dir.path().join("bin").join("julia");
+        std::fs::create_dir_all(julia_path.parent().unwrap()).unwrap();
+        std::fs::write(&julia_path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&julia_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_smoke_test_julia() {
+        let dir = write_fake_julia("echo 'julia version 1.7.0-beta3'");
+        smoke_test_julia(dir.path(), "1.7.0-beta3+0~x64").unwrap();
+        assert!(smoke_test_julia(dir.path(), "1.7.0-beta2+0~x64").is_err());
+        smoke_test_julia(dir.path(), "url-mychannel").unwrap();
+
+        let dir = write_fake_julia("echo 'cannot execute binary file' >&2; exit 126");
+        let err = smoke_test_julia(dir.path(), "1.6.2+0~x64").unwrap_err();
+        assert!(err.to_string().contains("cannot execute binary file"));
+
+        assert!(smoke_test_julia(Path::new("/does/not/exist"), "1.6.2+0~x64").is_err());
+
+        let dir = write_fake_julia("exec sleep 30");
+        let started = Instant::now();
+        let err = smoke_test_julia_with_timeout(dir.path(), "1.6.2+0~x64", Duration::from_millis(200)).unwrap_err();
+        assert!(err.to_string().contains("did not finish"));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_install_version_from_archive() {
+        let _home = use_test_juliaup_home();
+        let fullversion = "1.6.2+0~x64".to_string();
+        let version_db = version_db_with(&fullversion);
+        let mut config_data = empty_config();
+
+        let archive_dir = tempfile::tempdir().unwrap();
+        let archive_path = archive_dir.path().join("julia-1.6.2-linux-x86_64.tar.gz");
+        std::fs::write(&archive_path, fake_julia_archive("echo 'julia version 1.6.2'")).unwrap();
+
+        let wrong_sha256 = "0".repeat(64);
+        assert!(install_version_from_file(&fullversion, &archive_path, Some(&wrong_sha256), &mut config_data, &version_db, true).is_err());
+        assert!(!config_data.installed_versions.contains_key(&fullversion));
+
+        let sha256 = get_file_sha256(&archive_path).unwrap();
+        install_version_from_file(&fullversion, &archive_path, Some(&sha256), &mut config_data, &version_db, true).unwrap();
+        assert_installed(&config_data, &fullversion);
+        assert_eq!(config_data.installed_versions[&fullversion].from_file, Some(archive_path.to_string_lossy().into_owned()));
+
+        // Another channel for the same version reuses the installed files.
+        std::fs::write(&archive_path, "not an archive").unwrap();
+        install_version_from_file(&fullversion, &archive_path, None, &mut config_data, &version_db, true).unwrap();
+        assert_installed(&config_data, &fullversion);
+
+        let other_path = archive_dir.path().join("julia-1.5.3-linux-x86_64.tar.gz");
+        std::fs::write(&other_path, fake_julia_archive("echo 'julia version 1.5.3'")).unwrap();
+        let err = install_version_from_file(&fullversion, &other_path, None, &mut config_data, &version_db, true).unwrap_err();
+        assert!(err.to_string().contains("looks like Julia 1.5.3"));
+    }
+
+    #[test]
+    fn test_install_url_versions_with_same_file_name() {
+        let _home = use_test_juliaup_home();
+        let client = HttpClient::new(crate::http_client::HttpClientOptions::default()).unwrap();
+
+        let first_url = serve_archive(fake_julia_archive("echo first"));
+        let second_url = serve_archive(fake_julia_archive("echo second"));
+
+        let first = install_url_version_files("url-first", &first_url, None, &client, &Progress::hidden(), true).unwrap();
+        let second = install_url_version_files("url-second", &second_url, None, &client, &Progress::hidden(), true).unwrap();
+
+        let home_path = get_juliaup_home_path().unwrap();
+        let read_julia = |version: &JuliaupConfigVersion| {
+            std::fs::read_to_string(home_path.join(&version.path).join("bin").join("julia")).unwrap()
+        };
+        assert!(read_julia(&first).contains("echo first"));
+        assert!(read_julia(&second).contains("echo second"));
+    }
+
+    #[test]
+    fn test_install_version_from_folder() {
+        let _home = use_test_juliaup_home();
+        let fullversion = "1.6.1+0~x64".to_string();
+        let version_db = version_db_with(&fullversion);
+        let mut config_data = empty_config();
+
+        let source = write_fake_julia("echo 'julia version 1.6.1'");
+
+        // A folder has no checksum that could be checked.
+        let sha256 = "0".repeat(64);
+        assert!(install_version_from_file(&fullversion, source.path(), Some(&sha256), &mut config_data, &version_db, true).is_err());
+
+        install_version_from_file(&fullversion, source.path(), None, &mut config_data, &version_db, true).unwrap();
+        assert_installed(&config_data, &fullversion);
+        assert!(source.path().join("bin").join("julia").is_file());
+    }
 }
@@ -0,0 +1,28 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+/// The format of the results that commands print to stdout. Status messages,
+/// progress and errors always go to stderr as text.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    /// `format` is one of `text` or `json`, the default is `text`.
+    pub fn from_name(format: Option<&str>) -> OutputFormat {
+        match format {
+            Some("json") => OutputFormat::Json,
+            _ => OutputFormat::Text,
+        }
+    }
+}
+
+pub fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value).with_context(|| "Failed to serialize the output to JSON.")?;
+
+    println!("{}", json);
+
+    Ok(())
+}
@@ -0,0 +1,304 @@
+use console::style;
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
+use std::fmt::Display;
+use std::io::Read;
+use std::sync::{Mutex, OnceLock};
+
+// In plain mode a progress line is printed every time this many percent of a
+// download are done, or every `PLAIN_UNKNOWN_SIZE_STEP` bytes if the size of
+// the download is not known.
+const PLAIN_PERCENT_STEP: u64 = 10;
+const PLAIN_UNKNOWN_SIZE_STEP: u64 = 10 * 1024 * 1024;
+
+/// How juliaup reports progress and status messages.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ProgressMode {
+    /// Animated progress bars, for terminals.
+    Interactive,
+    /// One line every now and then, for logs and CI.
+    Plain,
+    /// Only warnings and errors.
+    Silent,
+}
+
+static PROGRESS_MODE: OnceLock<ProgressMode> = OnceLock::new();
+
+/// Picks the reporting backend from the command line options and the
+/// environment. `progress` is one of `auto`, `bar`, `plain` or `none`.
+/// Must be called before anything is reported, later calls have no effect.
+pub fn init_progress(quiet: bool, progress: Option<&str>) {
+    if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+
+    let mode = match (quiet, progress) {
+        (true, _) | (_, Some("none")) => ProgressMode::Silent,
+        (_, Some("plain")) => ProgressMode::Plain,
+        (_, Some("bar")) => ProgressMode::Interactive,
+        _ => detect_progress_mode(),
+    };
+
+    let _ = PROGRESS_MODE.set(mode);
+}
+
+fn detect_progress_mode() -> ProgressMode {
+    if atty::is(atty::Stream::Stderr) && std::env::var_os("CI").is_none() {
+        ProgressMode::Interactive
+    } else {
+        ProgressMode::Plain
+    }
+}
+
+pub fn progress_mode() -> ProgressMode {
+    *PROGRESS_MODE.get_or_init(detect_progress_mode)
+}
+
+/// Prints a status message like `Installing Julia 1.6.2`, where `action` is
+/// highlighted. Nothing is printed in silent mode.
+pub fn report_status(action: &str, message: impl Display) {
+    if progress_mode() != ProgressMode::Silent {
+        eprintln!("{} {}", style(action).green().bold().for_stderr(), message);
+    }
+}
+
+/// Prints an informational message. Nothing is printed in silent mode.
+pub fn report_info(message: impl Display) {
+    if progress_mode() != ProgressMode::Silent {
+        eprintln!("{}", message);
+    }
+}
+
+struct PlainProgress {
+    prefix: String,
+    length: Option<u64>,
+    position: u64,
+    last_reported_step: u64,
+    finished: bool,
+}
+
+impl PlainProgress {
+    fn get_step(&self) -> u64 {
+        match self.length {
+            Some(length) if length > 0 => self.position * 100 / length / PLAIN_PERCENT_STEP,
+            _ => self.position / PLAIN_UNKNOWN_SIZE_STEP,
+        }
+    }
+
+    fn report(&mut self) {
+        let step = self.get_step();
+
+        if step > self.last_reported_step {
+            self.last_reported_step = step;
+
+            match self.length {
+                Some(length) if length > 0 => eprintln!(
+                    "{} {}% ({} of {})",
+                    self.prefix,
+                    self.position * 100 / length,
+                    HumanBytes(self.position),
+                    HumanBytes(length)
+                ),
+                _ => eprintln!("{} {}", self.prefix, HumanBytes(self.position)),
+            }
+        }
+    }
+}
+
+enum Backend {
+    Bar(ProgressBar),
+    Plain(Mutex<PlainProgress>),
+    Silent,
+}
+
+/// Reports the progress of a single download with the backend selected by
+/// `init_progress`.
+pub struct Progress {
+    backend: Backend,
+}
+
+impl Progress {
+    pub fn new_download(prefix: &str) -> Progress {
+        let backend = match progress_mode() {
+            ProgressMode::Interactive => {
+                let pb = ProgressBar::new(0);
+                pb.set_prefix(prefix.to_string());
+                pb.set_style(ProgressStyle::default_bar()
+                    .template("{prefix:.cyan.bold} [{bar}] {bytes}/{total_bytes} eta: {eta}")
+                    .progress_chars("=> "));
+                Backend::Bar(pb)
+            }
+            ProgressMode::Plain => Backend::Plain(Mutex::new(PlainProgress {
+                prefix: prefix.to_string(),
+                length: None,
+                position: 0,
+                last_reported_step: 0,
+                finished: false,
+            })),
+            ProgressMode::Silent => Backend::Silent,
+        };
+
+        Progress { backend }
+    }
+
+    pub fn hidden() -> Progress {
+        Progress {
+            backend: Backend::Silent,
+        }
+    }
+
+    pub fn set_length(&self, length: u64) {
+        match &self.backend {
+            Backend::Bar(pb) => pb.set_length(length),
+            Backend::Plain(plain) => plain.lock().unwrap().length = Some(length),
+            Backend::Silent => {}
+        }
+    }
+
+    pub fn set_position(&self, position: u64) {
+        match &self.backend {
+            Backend::Bar(pb) => pb.set_position(position),
+            Backend::Plain(plain) => {
+                let mut plain = plain.lock().unwrap();
+                plain.position = position;
+                // A resumed download should not report the part we already had.
+                plain.last_reported_step = plain.get_step();
+            }
+            Backend::Silent => {}
+        }
+    }
+
+    pub fn inc(&self, delta: u64) {
+        match &self.backend {
+            Backend::Bar(pb) => pb.inc(delta),
+            Backend::Plain(plain) => {
+                let mut plain = plain.lock().unwrap();
+                plain.position += delta;
+                plain.report();
+            }
+            Backend::Silent => {}
+        }
+    }
+
+    /// Prints a message without messing up the progress display.
+    pub fn println(&self, message: impl AsRef<str>) {
+        match &self.backend {
+            Backend::Bar(pb) => pb.println(message),
+            Backend::Plain(_) => eprintln!("{}", message.as_ref()),
+            Backend::Silent => {}
+        }
+    }
+
+    pub fn finish(&self) {
+        match &self.backend {
+            Backend::Bar(pb) => pb.finish(),
+            Backend::Plain(plain) => {
+                let mut plain = plain.lock().unwrap();
+                // Nothing was reported for downloads that were already cached.
+                if !plain.finished && (plain.length.is_some() || plain.position > 0) {
+                    eprintln!("{} done ({})", plain.prefix, HumanBytes(plain.position));
+                }
+                plain.finished = true;
+            }
+            Backend::Silent => {}
+        }
+    }
+
+    /// Stops reporting without marking the download as done.
+    pub fn abandon(&self) {
+        if let Backend::Bar(pb) = &self.backend {
+            pb.abandon();
+        }
+    }
+
+    pub fn wrap_read<R: Read>(&self, read: R) -> ProgressRead<'_, R> {
+        ProgressRead {
+            progress: self,
+            inner: read,
+        }
+    }
+}
+
+pub struct ProgressRead<'a, R> {
+    progress: &'a Progress,
+    inner: R,
+}
+
+impl<R: Read> Read for ProgressRead<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.inc(n as u64);
+        Ok(n)
+    }
+}
+
+/// A set of downloads that run at the same time. In interactive mode their
+/// bars are drawn together, the other backends report each one on its own.
+pub struct ProgressGroup {
+    mp: Option<MultiProgress>,
+}
+
+impl ProgressGroup {
+    pub fn new() -> ProgressGroup {
+        let mp = match progress_mode() {
+            ProgressMode::Interactive => Some(MultiProgress::new()),
+            _ => None,
+        };
+
+        ProgressGroup { mp }
+    }
+
+    pub fn add_download(&self, prefix: &str) -> Progress {
+        let progress = Progress::new_download(prefix);
+
+        match (&self.mp, progress.backend) {
+            (Some(mp), Backend::Bar(pb)) => Progress {
+                backend: Backend::Bar(mp.add(pb)),
+            },
+            (_, backend) => Progress { backend },
+        }
+    }
+
+    /// Draws the progress until all downloads are finished or abandoned.
+    pub fn join(&self) {
+        if let Some(mp) = &self.mp {
+            // Nothing useful can be done if drawing the progress bars fails.
+            let _ = mp.join();
+        }
+    }
+}
+
+impl Default for ProgressGroup {
+    fn default() -> Self {
+        ProgressGroup::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_progress_steps() {
+        let mut plain = PlainProgress {
+            prefix: String::new(),
+            length: Some(1000),
+            position: 0,
+            last_reported_step: 0,
+            finished: false,
+        };
+
+        plain.position = 99;
+        assert_eq!(plain.get_step(), 0);
+        plain.position = 250;
+        assert_eq!(plain.get_step(), 2);
+        plain.report();
+        assert_eq!(plain.last_reported_step, 2);
+        plain.position = 1000;
+        assert_eq!(plain.get_step(), 10);
+
+        plain.length = None;
+        plain.position = 3 * PLAIN_UNKNOWN_SIZE_STEP + 1;
+        assert_eq!(plain.get_step(), 3);
+    }
+}
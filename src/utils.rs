@@ -1,13 +1,15 @@
 use anyhow::{anyhow, bail, Context, Result};
 use semver::Version;
-use std::path::PathBuf;
+use std::io::Write;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 
 pub fn get_juliaup_home_path() -> Result<PathBuf> {
     let entry_sep = if std::env::consts::OS == "windows" {';'} else {':'};
 
     let path = match std::env::var("JULIA_DEPOT_PATH") {
         Ok(val) => {
-            let path = PathBuf::from(val.to_string().split(entry_sep).next().unwrap()); // We can unwrap here because even when we split an empty string we should get a first element.
+            let path = PathBuf::from(val.split(entry_sep).next().unwrap()); // We can unwrap here because even when we split an empty string we should get a first element.
 
             if !path.is_absolute() {
                 bail!("The `JULIA_DEPOT_PATH` environment variable contains a value that resolves to an an invalid path `{}`.", path.display());
@@ -43,6 +45,18 @@ pub fn get_juliaupconfig_path() -> Result<PathBuf> {
     Ok(path)
 }
 
+pub fn get_juliaup_cache_path() -> Result<PathBuf> {
+    let path = get_juliaup_home_path()?.join("cache");
+
+    Ok(path)
+}
+
+pub fn get_juliaup_manifests_path() -> Result<PathBuf> {
+    let path = get_juliaup_home_path()?.join("manifests");
+
+    Ok(path)
+}
+
 pub fn get_arch() -> Result<String> {
     if std::env::consts::ARCH == "x86" {
         return Ok("x86".to_string());
@@ -76,6 +90,95 @@ pub fn parse_versionstring(value: &String) -> Result<(String, Version)> {
     Ok((platform.to_string(), version))
 }
 
+/// Checks that `channel` can be used for a channel installed from a url.
+/// The version and the folder of such a channel are named after it, so only
+/// characters that are safe in a file name are allowed.
+pub fn check_url_channel_name(channel: &str) -> Result<()> {
+    if channel.is_empty() || !channel.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.') {
+        bail!("'{}' is not a valid channel name, only letters, digits, `-`, `_` and `.` are allowed.", channel);
+    }
+
+    Ok(())
+}
+
+pub fn get_file_sha256(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open `{}` to compute its hash.", path.display()))?;
+
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to read `{}` to compute its hash.", path.display()))?;
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+pub fn verify_file_sha256(path: &Path, expected_sha256: &str) -> Result<()> {
+    let actual_sha256 = get_file_sha256(path)?;
+
+    if !actual_sha256.eq_ignore_ascii_case(expected_sha256.trim()) {
+        bail!(
+            "The SHA-256 checksum of `{}` is {}, but {} was expected.",
+            path.display(),
+            actual_sha256,
+            expected_sha256.trim()
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether we can ask the user questions on the terminal.
+pub fn is_interactive() -> bool {
+    atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stderr)
+}
+
+/// Asks a yes/no question on the terminal, anything but `y` or `yes` counts
+/// as no.
+pub fn prompt_confirmation(question: &str) -> Result<bool> {
+    eprint!("{} [y/N] ", question);
+    std::io::stderr().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .with_context(|| "Failed to read the answer from the terminal.")?;
+
+    Ok(matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes"))
+}
+
+/// A temporary juliaup folder for one test, see `use_test_juliaup_home`.
+#[cfg(test)]
+pub(crate) struct TestJuliaupHome {
+    // Declared first so that the folder is deleted before the next test can
+    // take the lock.
+    home: tempfile::TempDir,
+    _lock: std::sync::MutexGuard<'static, ()>,
+}
+
+#[cfg(test)]
+impl TestJuliaupHome {
+    pub(crate) fn path(&self) -> &Path {
+        self.home.path()
+    }
+}
+
+/// Points `JULIA_DEPOT_PATH` at a new temporary folder for as long as the
+/// returned value lives. `JULIA_DEPOT_PATH` is shared by the whole process,
+/// so tests that use this run one after the other.
+#[cfg(test)]
+pub(crate) fn use_test_juliaup_home() -> TestJuliaupHome {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    // A test that failed while holding the lock doesn't affect the next one,
+    // which gets its own folder anyway.
+    let lock = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+
+    let home = tempfile::tempdir().unwrap();
+    std::env::set_var("JULIA_DEPOT_PATH", home.path());
+
+    TestJuliaupHome { home, _lock: lock }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -100,6 +203,14 @@ mod tests {
         let s = "1.1.1+0~x64";
         let (p,v) = parse_versionstring(&s.to_owned()).unwrap();
         assert_eq!(p, "x64");
-        assert_eq!(v, Version::new(1, 1, 1));
+        assert_eq!(v, Version::parse("1.1.1+0").unwrap());
+    }
+
+    #[test]
+    fn test_check_url_channel_name() {
+        assert!(check_url_channel_name("nightly-1.8_x64").is_ok());
+        assert!(check_url_channel_name("").is_err());
+        assert!(check_url_channel_name("../nightly").is_err());
+        assert!(check_url_channel_name("C:\\nightly").is_err());
     }
 }
@@ -25,7 +25,7 @@ pub fn load_versions_db() -> Result<JuliaupVersionDB> {
     //     return Ok(db);
     // }
 
-    let db: JuliaupVersionDB = serde_json::from_str(&vendored_db)
+    let db: JuliaupVersionDB = serde_json::from_str(vendored_db)
         .with_context(|| "Failed to parse vendored version db.")?;
 
     Ok(db)